@@ -9,6 +9,8 @@ import { lexMarketPubkey, stubOracle } from '../settings';
  export default function NewOrder({userAccount}: {userAccount: IdlAccounts<Balex>['userAccount']}) {
      const [amount, setAmount] = useState<number>(0)
      const [rate, setRate] = useState<number>(0)
+     const [orderType, setOrderType] = useState<string>('limit')
+     const [selfTradeBehavior, setSelfTradeBehavior] = useState<string>('abortTransaction')
      const wallet = useAnchorWallet();
      const program = useProgram(wallet);
 
@@ -16,7 +18,8 @@ import { lexMarketPubkey, stubOracle } from '../settings';
          const [userPub, bump] = await getUserAccount(wallet);
          const [orderbook, eventQueue, asks, bids] = await getMarketAccounts(program)
 
-         await program.rpc.newOrder(bump, type, new anchor.BN(rate), new anchor.BN(amount), {
+         // Orders don't expire and have no client order id from the app, loan term is the market's
+         await program.rpc.newOrder(bump, type, new anchor.BN(rate), new anchor.BN(amount), new anchor.BN(0), { [orderType]: {} }, { [selfTradeBehavior]: {} }, new anchor.BN(0), {
              accounts: {
                  owner: wallet.publicKey,
                  userAccount: userPub,
@@ -43,6 +46,23 @@ import { lexMarketPubkey, stubOracle } from '../settings';
                 <span className='label'>Interest rate</span>
                 <input defaultValue={rate} onChange={(e) => setRate(parseInt(e.target.value))} />
             </div>
+            <div className='text'>
+                <span className='label'>Order type</span>
+                <select value={orderType} onChange={(e) => setOrderType(e.target.value)}>
+                    <option value='limit'>Limit</option>
+                    <option value='postOnly'>Post only</option>
+                    <option value='immediateOrCancel'>Immediate or cancel</option>
+                    <option value='fillOrKill'>Fill or kill</option>
+                </select>
+            </div>
+            <div className='text'>
+                <span className='label'>Self trade</span>
+                <select value={selfTradeBehavior} onChange={(e) => setSelfTradeBehavior(e.target.value)}>
+                    <option value='abortTransaction'>Abort</option>
+                    <option value='decrementTake'>Decrement take</option>
+                    <option value='cancelProvide'>Cancel provide</option>
+                </select>
+            </div>
 
             <div className='flex mt-5'>
                 <button onClick={() => newOrder(0)}>Borrow</button>
@@ -4,9 +4,8 @@ import { GiPayMoney } from "react-icons/gi";
 import { IdlAccounts } from '@project-serum/anchor';
 import { Balex } from '../types/balex';
 import { useAnchorWallet } from '@solana/wallet-adapter-react';
-import { DebtType, getDebtAsOfNow, getMarketAccounts, getUserAccount, useInterval, useProgram } from '../utils';
+import { getDebtAsOfNow, getUserAccount, getUserDebts, useInterval, useProgram } from '../utils';
 import * as anchor from '@project-serum/anchor';
-import { getPriceFromKey, MarketState } from '@bonfida/aaob';
 import { lexMarketPubkey, programId } from '../settings';
 import { PublicKey } from '@solana/web3.js';
 
@@ -15,7 +14,8 @@ export default function OpenDebts({ userAccount }: { userAccount: IdlAccounts<Ba
     const program = useProgram(wallet);
 
     type DebtRow = {
-        'id': number,
+        'id': anchor.BN,
+        'lender': PublicKey,
         'type': string,
         'qty': number,
         'interest': number,
@@ -23,21 +23,25 @@ export default function OpenDebts({ userAccount }: { userAccount: IdlAccounts<Ba
         'remaining': number,
     }
 
-    async function settleDown(debt_id: number) {
-        let marketData = await program.account.lexMarket.fetch(lexMarketPubkey);
-        let debt: DebtType = marketData.debts[debt_id]
+    // Amount more than the debt settles it fully
+    const SETTLE_ALL = new anchor.BN("18446744073709551615");
+
+    async function settleDown(debt: DebtRow) {
         let [borrowAccount, bump] = await getUserAccount(wallet)
         //Make sure user has balance (but not right now), or error if doesn't
         const lenderAccount = (await PublicKey.findProgramAddress(
             [lexMarketPubkey.toBuffer(), debt.lender.toBuffer()], programId
         ))[0];
-        console.log(lenderAccount.toString())
-        await program.rpc.settleDebt(bump, debt_id, {
+        const debtAccount = (await PublicKey.findProgramAddress(
+            [lexMarketPubkey.toBuffer(), debt.id.toArrayLike(Buffer, 'le', 8)], programId
+        ))[0];
+        await program.rpc.settleDebt(bump, debt.id, SETTLE_ALL, {
             accounts: {
                 owner: wallet.publicKey,
                 borrowerAccount: borrowAccount,
                 lenderAccount: lenderAccount,
-                market: lexMarketPubkey
+                market: lexMarketPubkey,
+                debt: debtAccount
             }
         })
     }
@@ -45,17 +49,13 @@ export default function OpenDebts({ userAccount }: { userAccount: IdlAccounts<Ba
     const [debts, setDebts] = useState<DebtRow[]>([])
 
     async function updateDebts() {
-        let marketData = await program.account.lexMarket.fetch(lexMarketPubkey);
-        console.log(marketData.debts)
+        let userDebts = await getUserDebts(wallet.publicKey, program);
 
         let curr_debts: DebtRow[] = []
-        for (let i = 0; i < userAccount.openDebtsCnt; i++) {
-            let debt_id = userAccount.openDebts[i];
-            let debt: DebtType = marketData.debts[debt_id]
-            console.log(debt);
-
+        for (let debt of userDebts) {
             curr_debts.push({
-                id: debt_id,
+                id: debt.id,
+                lender: debt.lender,
                 type: (debt.borrower.equals(wallet.publicKey) ? 'Borrow' : 'Lend'),
                 qty: debt.qty.toNumber(),
                 interest: debt.interestRate.toNumber(),
@@ -93,7 +93,7 @@ export default function OpenDebts({ userAccount }: { userAccount: IdlAccounts<Ba
 
             {debts.map((debt) => (
 
-                <div key={debt.id} className='text'>
+                <div key={debt.id.toString()} className='text'>
                     <span className='label' style={{ flex: 3 }}>{debt.type}</span>
                     <span className='label' style={{ flex: 3 }}>{debt.qty}</span>
                     <span className='label' style={{ flex: 3 }}>{debt.interest}%</span>
@@ -101,7 +101,7 @@ export default function OpenDebts({ userAccount }: { userAccount: IdlAccounts<Ba
                     <span className='label' style={{ flex: 5 }}>{debt.remaining}</span>
                     <span className='label' style={{ flex: 2, display: 'flex', justifyContent: 'center' }}>
                         {debt.type == "Borrow" &&
-                            <GiPayMoney style={{ color: 'white', cursor: 'pointer' }} onClick={() => settleDown(debt.id)} />
+                            <GiPayMoney style={{ color: 'white', cursor: 'pointer' }} onClick={() => settleDown(debt)} />
                         }
                     </span>
                 </div>
@@ -4,7 +4,7 @@ import { AiOutlineDelete } from "react-icons/ai";
 import { IdlAccounts } from '@project-serum/anchor';
 import { Balex } from '../types/balex';
 import { useAnchorWallet } from '@solana/wallet-adapter-react';
-import { getMarketAccounts, getUserAccount, useInterval, useProgram } from '../utils';
+import { getMarketAccounts, getOpenOrderIds, getUserAccount, useInterval, useProgram } from '../utils';
 import * as anchor from '@project-serum/anchor';
 import { getPriceFromKey, MarketState } from '@bonfida/aaob';
 import { lexMarketPubkey } from '../settings';
@@ -23,10 +23,11 @@ export default function OpenOrders({ userAccount }: { userAccount: IdlAccounts<B
         const marketData = await MarketState.retrieve(connection, orderbook, 'confirmed')
         const askData = await marketData.loadAsksSlab(connection, 'confirmed')
         const bidData = await marketData.loadBidsSlab(connection, 'confirmed')
+        const [userPub] = await getUserAccount(wallet)
+        const orderIds = await getOpenOrderIds(userPub, userAccount, program)
 
         let openOrders: OrderType[] = []
-        for (let i = 0; i < userAccount.openOrdersCnt; i++) {
-            const order_id = userAccount.openOrders[i]
+        for (const order_id of orderIds) {
 
             let askNode = askData.getNodeByKey(order_id as any);
             if (askNode) {
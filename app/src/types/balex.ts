@@ -15,6 +15,21 @@ export type Balex = {
           "isMut": true,
           "isSigner": false
         },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseMint",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "quoteMint",
+          "isMut": false,
+          "isSigner": false
+        },
         {
           "name": "baseVault",
           "isMut": false,
@@ -62,21 +77,129 @@ export type Balex = {
           "type": "u8"
         },
         {
-          "name": "baseMint",
-          "type": "publicKey"
+          "name": "oracleType",
+          "type": {
+            "defined": "OracleType"
+          }
         },
         {
-          "name": "quoteMint",
-          "type": "publicKey"
+          "name": "loanTerm",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "updateMarketParams",
+      "accounts": [
+        {
+          "name": "riskAdmin",
+          "isMut": false,
+          "isSigner": true
         },
         {
-          "name": "oracleType",
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "params",
           "type": {
-            "defined": "OracleType"
+            "defined": "MarketParams"
+          }
+        }
+      ]
+    },
+    {
+      "name": "setMarketStatus",
+      "accounts": [
+        {
+          "name": "authority",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "status",
+          "type": {
+            "defined": "MarketStatus"
           }
         }
       ]
     },
+    {
+      "name": "setRoles",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "riskAdmin",
+          "type": "publicKey"
+        },
+        {
+          "name": "feeAdmin",
+          "type": "publicKey"
+        },
+        {
+          "name": "guardian",
+          "type": "publicKey"
+        }
+      ]
+    },
+    {
+      "name": "proposeAdmin",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "newAdmin",
+          "type": "publicKey"
+        }
+      ]
+    },
+    {
+      "name": "acceptAdmin",
+      "accounts": [
+        {
+          "name": "pendingAdmin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
     {
       "name": "initializeAccount",
       "accounts": [
@@ -108,6 +231,76 @@ export type Balex = {
         }
       ]
     },
+    {
+      "name": "resizeUserAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "openOrdersCapacity",
+          "type": "u16"
+        },
+        {
+          "name": "openDebtsCapacity",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "migrateUserAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        }
+      ]
+    },
     {
       "name": "deposit",
       "accounts": [
@@ -248,6 +441,26 @@ export type Balex = {
         {
           "name": "qty",
           "type": "u64"
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64"
+        },
+        {
+          "name": "orderType",
+          "type": {
+            "defined": "OrderType"
+          }
+        },
+        {
+          "name": "selfTradeBehavior",
+          "type": {
+            "defined": "SelfTradeBehavior"
+          }
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64"
         }
       ]
     },
@@ -307,12 +520,12 @@ export type Balex = {
       ]
     },
     {
-      "name": "cancelRiskyOrder",
+      "name": "cancelByClientOrderId",
       "accounts": [
         {
           "name": "owner",
           "isMut": true,
-          "isSigner": false
+          "isSigner": true
         },
         {
           "name": "userAccount",
@@ -344,11 +557,6 @@ export type Balex = {
           "isMut": true,
           "isSigner": false
         },
-        {
-          "name": "priceOracle",
-          "isMut": false,
-          "isSigner": false
-        },
         {
           "name": "systemProgram",
           "isMut": false,
@@ -361,14 +569,24 @@ export type Balex = {
           "type": "u8"
         },
         {
-          "name": "orderId",
-          "type": "u128"
+          "name": "clientOrderId",
+          "type": "u64"
         }
       ]
     },
     {
-      "name": "consumeOrderEvents",
+      "name": "cancelAllOrders",
       "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
         {
           "name": "market",
           "isMut": true,
@@ -384,6 +602,16 @@ export type Balex = {
           "isMut": true,
           "isSigner": false
         },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
         {
           "name": "systemProgram",
           "isMut": false,
@@ -392,13 +620,19 @@ export type Balex = {
       ],
       "args": [
         {
-          "name": "maxIterations",
-          "type": "u64"
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "sideNum",
+          "type": {
+            "option": "u8"
+          }
         }
       ]
     },
     {
-      "name": "withdraw",
+      "name": "replaceOrder",
       "accounts": [
         {
           "name": "owner",
@@ -412,31 +646,36 @@ export type Balex = {
         },
         {
           "name": "market",
-          "isMut": false,
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "marketSigner",
-          "isMut": false,
+          "name": "eventQueue",
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "vault",
+          "name": "orderbook",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenDest",
+          "name": "asks",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "priceOracle",
-          "isMut": false,
+          "name": "bids",
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenProgram",
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
           "isMut": false,
           "isSigner": false
         }
@@ -447,33 +686,86 @@ export type Balex = {
           "type": "u8"
         },
         {
-          "name": "amount",
+          "name": "orderId",
+          "type": "u128"
+        },
+        {
+          "name": "interestRate",
+          "type": "u64"
+        },
+        {
+          "name": "qty",
+          "type": "u64"
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64"
+        },
+        {
+          "name": "orderType",
+          "type": {
+            "defined": "OrderType"
+          }
+        },
+        {
+          "name": "selfTradeBehavior",
+          "type": {
+            "defined": "SelfTradeBehavior"
+          }
+        },
+        {
+          "name": "clientOrderId",
           "type": "u64"
         }
       ]
     },
     {
-      "name": "settleDebt",
+      "name": "cancelRiskyOrder",
       "accounts": [
         {
           "name": "owner",
           "isMut": true,
-          "isSigner": true
+          "isSigner": false
         },
         {
-          "name": "borrowerAccount",
+          "name": "userAccount",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "lenderAccount",
+          "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "market",
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
           "isMut": true,
           "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
         }
       ],
       "args": [
@@ -482,51 +774,128 @@ export type Balex = {
           "type": "u8"
         },
         {
-          "name": "debtId",
-          "type": "u16"
+          "name": "orderId",
+          "type": "u128"
         }
       ]
     },
     {
-      "name": "liquidateDebts",
+      "name": "purgeExpiredOrders",
       "accounts": [
         {
-          "name": "liquidator",
-          "isMut": false,
-          "isSigner": true
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
         },
         {
-          "name": "borrowerAccount",
+          "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "market",
+          "name": "eventQueue",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "marketSigner",
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
           "isMut": false,
           "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "maxOrders",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "consumeOrderEvents",
+      "accounts": [
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
         },
         {
-          "name": "baseVault",
+          "name": "eventQueue",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "quoteVault",
+          "name": "orderbook",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenBaseSrc",
+          "name": "payer",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "rewardTarget",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenQuoteDest",
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "maxIterations",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "withdraw",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenDest",
           "isMut": true,
           "isSigner": false
         },
@@ -543,372 +912,160 @@ export type Balex = {
       ],
       "args": [
         {
-          "name": "debtsId",
-          "type": {
-            "vec": "u16"
-          }
+          "name": "bump",
+          "type": "u8"
         },
         {
-          "name": "debtsAmount",
-          "type": {
-            "vec": "u64"
-          }
+          "name": "amount",
+          "type": "u64"
         }
       ]
     },
     {
-      "name": "orderDummy",
+      "name": "settleDebt",
       "accounts": [
         {
-          "name": "userAccount",
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "lenderAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "debt",
           "isMut": true,
           "isSigner": false
         }
       ],
-      "args": []
-    }
-  ],
-  "accounts": [
-    {
-      "name": "stubPrice",
-      "type": {
-        "kind": "struct",
-        "fields": [
-          {
-            "name": "price",
-            "type": "i64"
-          },
-          {
-            "name": "conf",
-            "type": "u64"
-          }
-        ]
-      }
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "debtId",
+          "type": "u64"
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
     },
     {
-      "name": "lexMarket",
-      "type": {
-        "kind": "struct",
-        "fields": [
-          {
-            "name": "baseMint",
-            "type": "publicKey"
-          },
-          {
-            "name": "quoteMint",
-            "type": "publicKey"
-          },
-          {
-            "name": "baseVault",
-            "type": "publicKey"
-          },
-          {
-            "name": "quoteVault",
-            "type": "publicKey"
-          },
-          {
-            "name": "priceOracle",
-            "type": "publicKey"
-          },
-          {
-            "name": "orderbook",
-            "type": "publicKey"
-          },
-          {
-            "name": "admin",
-            "type": "publicKey"
-          },
-          {
-            "name": "debts",
-            "type": {
-              "array": [
-                {
-                  "defined": "Debt"
-                },
-                256
-              ]
-            }
-          },
-          {
-            "name": "overCollateralPercent",
-            "type": "u8"
-          },
-          {
-            "name": "signerBump",
-            "type": "u8"
-          },
-          {
-            "name": "oracleType",
-            "type": {
-              "defined": "OracleType"
-            }
-          },
-          {
-            "name": "padding",
-            "type": {
-              "array": [
-                "u8",
-                5
-              ]
-            }
-          }
-        ]
-      }
-    },
-    {
-      "name": "userAccount",
-      "type": {
-        "kind": "struct",
-        "fields": [
-          {
-            "name": "owner",
-            "type": "publicKey"
-          },
-          {
-            "name": "baseFree",
-            "type": "u64"
-          },
-          {
-            "name": "baseLocked",
-            "type": "u64"
-          },
-          {
-            "name": "baseOpenLend",
-            "type": "u64"
-          },
-          {
-            "name": "baseOpenBorrow",
-            "type": "u64"
-          },
-          {
-            "name": "quoteTotal",
-            "type": "u64"
-          },
-          {
-            "name": "openOrders",
-            "type": {
-              "array": [
-                "u128",
-                16
-              ]
-            }
-          },
-          {
-            "name": "openDebts",
-            "type": {
-              "array": [
-                "u16",
-                16
-              ]
-            }
-          },
-          {
-            "name": "openOrdersCnt",
-            "type": "u8"
-          },
-          {
-            "name": "openDebtsCnt",
-            "type": "u8"
-          },
-          {
-            "name": "padding",
-            "type": {
-              "array": [
-                "u8",
-                6
-              ]
-            }
-          }
-        ]
-      }
-    }
-  ],
-  "types": [
-    {
-      "name": "Debt",
-      "type": {
-        "kind": "struct",
-        "fields": [
-          {
-            "name": "lender",
-            "type": "publicKey"
-          },
-          {
-            "name": "borrower",
-            "type": "publicKey"
-          },
-          {
-            "name": "timestamp",
-            "type": "i64"
-          },
-          {
-            "name": "interestRate",
-            "type": "u64"
-          },
-          {
-            "name": "qty",
-            "type": "u64"
-          },
-          {
-            "name": "liquidQty",
-            "type": "u64"
-          }
-        ]
-      }
-    },
-    {
-      "name": "OracleType",
-      "type": {
-        "kind": "enum",
-        "variants": [
-          {
-            "name": "Stub"
-          },
-          {
-            "name": "Pyth"
-          }
-        ]
-      }
-    }
-  ]
-};
-
-export const IDL: Balex = {
-  "version": "0.1.0",
-  "name": "balex",
-  "instructions": [
-    {
-      "name": "initializeMarket",
+      "name": "repayFromWallet",
       "accounts": [
         {
-          "name": "admin",
-          "isMut": true,
+          "name": "payer",
+          "isMut": false,
           "isSigner": true
         },
         {
-          "name": "market",
+          "name": "tokenBaseSrc",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "baseVault",
-          "isMut": false,
-          "isSigner": false
-        },
-        {
-          "name": "quoteVault",
-          "isMut": false,
-          "isSigner": false
-        },
-        {
-          "name": "eventQueue",
+          "name": "borrowerAccount",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "orderbook",
+          "name": "lenderAccount",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "asks",
+          "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "bids",
+          "name": "debt",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "priceOracle",
-          "isMut": false,
+          "name": "baseVault",
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "systemProgram",
+          "name": "tokenProgram",
           "isMut": false,
           "isSigner": false
         }
       ],
       "args": [
         {
-          "name": "signerBump",
-          "type": "u8"
-        },
-        {
-          "name": "baseMint",
-          "type": "publicKey"
-        },
-        {
-          "name": "quoteMint",
-          "type": "publicKey"
+          "name": "debtId",
+          "type": "u64"
         },
         {
-          "name": "oracleType",
-          "type": {
-            "defined": "OracleType"
-          }
+          "name": "amount",
+          "type": "u64"
         }
       ]
     },
     {
-      "name": "initializeAccount",
+      "name": "liquidateDebts",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
+          "name": "liquidator",
+          "isMut": false,
           "isSigner": true
         },
         {
-          "name": "market",
-          "isMut": false,
+          "name": "borrowerAccount",
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "userAccount",
+          "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "systemProgram",
+          "name": "marketSigner",
           "isMut": false,
           "isSigner": false
-        }
-      ],
-      "args": [
-        {
-          "name": "bump",
-          "type": "u8"
-        }
-      ]
-    },
-    {
-      "name": "deposit",
-      "accounts": [
+        },
         {
-          "name": "owner",
+          "name": "baseVault",
           "isMut": true,
-          "isSigner": true
+          "isSigner": false
         },
         {
-          "name": "userAccount",
+          "name": "quoteVault",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "market",
-          "isMut": false,
+          "name": "tokenBaseSrc",
+          "isMut": true,
           "isSigner": false
         },
         {
-          "name": "vault",
+          "name": "tokenQuoteDest",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenSource",
-          "isMut": true,
+          "name": "priceOracle",
+          "isMut": false,
           "isSigner": false
         },
         {
@@ -919,123 +1076,66 @@ export const IDL: Balex = {
       ],
       "args": [
         {
-          "name": "bump",
-          "type": "u8"
+          "name": "debtsId",
+          "type": {
+            "vec": "u64"
+          }
         },
         {
-          "name": "amount",
-          "type": "u64"
+          "name": "debtsAmount",
+          "type": {
+            "vec": "u64"
+          }
         }
       ]
     },
     {
-      "name": "setStubPrice",
+      "name": "closeDebt",
       "accounts": [
         {
-          "name": "admin",
+          "name": "debt",
           "isMut": true,
-          "isSigner": true
+          "isSigner": false
         },
         {
-          "name": "stubPrice",
+          "name": "payer",
           "isMut": true,
-          "isSigner": true
-        },
-        {
-          "name": "systemProgram",
-          "isMut": false,
           "isSigner": false
         }
       ],
-      "args": [
-        {
-          "name": "price",
-          "type": "i64"
-        },
-        {
-          "name": "conf",
-          "type": "u64"
-        }
-      ]
+      "args": []
     },
     {
-      "name": "newOrder",
+      "name": "updateLiquidationAuction",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
-          "isSigner": true
-        },
-        {
-          "name": "userAccount",
+          "name": "borrowerAccount",
           "isMut": true,
           "isSigner": false
         },
         {
           "name": "market",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "eventQueue",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "orderbook",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "asks",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "bids",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "priceOracle",
           "isMut": false,
           "isSigner": false
         },
         {
-          "name": "systemProgram",
+          "name": "priceOracle",
           "isMut": false,
           "isSigner": false
         }
       ],
-      "args": [
-        {
-          "name": "bump",
-          "type": "u8"
-        },
-        {
-          "name": "sideNum",
-          "type": "u8"
-        },
-        {
-          "name": "interestRate",
-          "type": "u64"
-        },
-        {
-          "name": "qty",
-          "type": "u64"
-        }
-      ]
+      "args": []
     },
     {
-      "name": "cancelMyOrder",
+      "name": "resolveBadDebt",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
+          "name": "liquidator",
+          "isMut": false,
           "isSigner": true
         },
         {
-          "name": "userAccount",
+          "name": "borrowerAccount",
           "isMut": true,
           "isSigner": false
         },
@@ -1045,149 +1145,137 @@ export const IDL: Balex = {
           "isSigner": false
         },
         {
-          "name": "eventQueue",
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "orderbook",
+          "name": "quoteVault",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "asks",
+          "name": "tokenBaseSrc",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "bids",
+          "name": "tokenQuoteDest",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "systemProgram",
+          "name": "priceOracle",
           "isMut": false,
           "isSigner": false
-        }
-      ],
-      "args": [
-        {
-          "name": "bump",
-          "type": "u8"
         },
         {
-          "name": "orderId",
-          "type": "u128"
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
         }
-      ]
+      ],
+      "args": []
     },
     {
-      "name": "cancelRiskyOrder",
+      "name": "orderDummy",
       "accounts": [
-        {
-          "name": "owner",
-          "isMut": true,
-          "isSigner": false
-        },
         {
           "name": "userAccount",
           "isMut": true,
           "isSigner": false
-        },
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "claimFees",
+      "accounts": [
         {
-          "name": "market",
-          "isMut": true,
-          "isSigner": false
+          "name": "feeAdmin",
+          "isMut": false,
+          "isSigner": true
         },
         {
-          "name": "eventQueue",
+          "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "orderbook",
-          "isMut": true,
+          "name": "marketSigner",
+          "isMut": false,
           "isSigner": false
         },
         {
-          "name": "asks",
+          "name": "baseVault",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "bids",
+          "name": "tokenDest",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "priceOracle",
-          "isMut": false,
-          "isSigner": false
-        },
-        {
-          "name": "systemProgram",
+          "name": "tokenProgram",
           "isMut": false,
           "isSigner": false
         }
       ],
-      "args": [
-        {
-          "name": "bump",
-          "type": "u8"
-        },
-        {
-          "name": "orderId",
-          "type": "u128"
-        }
-      ]
+      "args": []
     },
     {
-      "name": "consumeOrderEvents",
+      "name": "depositInsurance",
       "accounts": [
+        {
+          "name": "depositor",
+          "isMut": false,
+          "isSigner": true
+        },
         {
           "name": "market",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "eventQueue",
+          "name": "baseVault",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "orderbook",
+          "name": "tokenSrc",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "systemProgram",
+          "name": "tokenProgram",
           "isMut": false,
           "isSigner": false
         }
       ],
       "args": [
         {
-          "name": "maxIterations",
+          "name": "amount",
           "type": "u64"
         }
       ]
     },
     {
-      "name": "withdraw",
+      "name": "retireInsurance",
       "accounts": [
         {
-          "name": "owner",
-          "isMut": true,
+          "name": "admin",
+          "isMut": false,
           "isSigner": true
         },
-        {
-          "name": "userAccount",
-          "isMut": true,
-          "isSigner": false
-        },
         {
           "name": "market",
-          "isMut": false,
+          "isMut": true,
           "isSigner": false
         },
         {
@@ -1196,7 +1284,7 @@ export const IDL: Balex = {
           "isSigner": false
         },
         {
-          "name": "vault",
+          "name": "baseVault",
           "isMut": true,
           "isSigner": false
         },
@@ -1205,30 +1293,16 @@ export const IDL: Balex = {
           "isMut": true,
           "isSigner": false
         },
-        {
-          "name": "priceOracle",
-          "isMut": false,
-          "isSigner": false
-        },
         {
           "name": "tokenProgram",
           "isMut": false,
           "isSigner": false
         }
       ],
-      "args": [
-        {
-          "name": "bump",
-          "type": "u8"
-        },
-        {
-          "name": "amount",
-          "type": "u64"
-        }
-      ]
+      "args": []
     },
     {
-      "name": "settleDebt",
+      "name": "closeUserAccount",
       "accounts": [
         {
           "name": "owner",
@@ -1236,18 +1310,13 @@ export const IDL: Balex = {
           "isSigner": true
         },
         {
-          "name": "borrowerAccount",
-          "isMut": true,
-          "isSigner": false
-        },
-        {
-          "name": "lenderAccount",
+          "name": "userAccount",
           "isMut": true,
           "isSigner": false
         },
         {
           "name": "market",
-          "isMut": true,
+          "isMut": false,
           "isSigner": false
         }
       ],
@@ -1255,25 +1324,16 @@ export const IDL: Balex = {
         {
           "name": "bump",
           "type": "u8"
-        },
-        {
-          "name": "debtId",
-          "type": "u16"
         }
       ]
     },
     {
-      "name": "liquidateDebts",
+      "name": "closeMarket",
       "accounts": [
         {
-          "name": "liquidator",
-          "isMut": false,
-          "isSigner": true
-        },
-        {
-          "name": "borrowerAccount",
+          "name": "admin",
           "isMut": true,
-          "isSigner": false
+          "isSigner": true
         },
         {
           "name": "market",
@@ -1296,18 +1356,23 @@ export const IDL: Balex = {
           "isSigner": false
         },
         {
-          "name": "tokenBaseSrc",
+          "name": "eventQueue",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "tokenQuoteDest",
+          "name": "orderbook",
           "isMut": true,
           "isSigner": false
         },
         {
-          "name": "priceOracle",
-          "isMut": false,
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
           "isSigner": false
         },
         {
@@ -1316,30 +1381,6 @@ export const IDL: Balex = {
           "isSigner": false
         }
       ],
-      "args": [
-        {
-          "name": "debtsId",
-          "type": {
-            "vec": "u16"
-          }
-        },
-        {
-          "name": "debtsAmount",
-          "type": {
-            "vec": "u64"
-          }
-        }
-      ]
-    },
-    {
-      "name": "orderDummy",
-      "accounts": [
-        {
-          "name": "userAccount",
-          "isMut": true,
-          "isSigner": false
-        }
-      ],
       "args": []
     }
   ],
@@ -1356,6 +1397,62 @@ export const IDL: Balex = {
           {
             "name": "conf",
             "type": "u64"
+          },
+          {
+            "name": "authority",
+            "type": "publicKey"
+          }
+        ]
+      }
+    },
+    {
+      "name": "debt",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "publicKey"
+          },
+          {
+            "name": "lender",
+            "type": "publicKey"
+          },
+          {
+            "name": "borrower",
+            "type": "publicKey"
+          },
+          {
+            "name": "id",
+            "type": "u64"
+          },
+          {
+            "name": "timestamp",
+            "type": "i64"
+          },
+          {
+            "name": "interestRate",
+            "type": "u64"
+          },
+          {
+            "name": "qty",
+            "type": "u64"
+          },
+          {
+            "name": "liquidQty",
+            "type": "u64"
+          },
+          {
+            "name": "accruedInterest",
+            "type": "u64"
+          },
+          {
+            "name": "maturity",
+            "type": "i64"
+          },
+          {
+            "name": "payer",
+            "type": "publicKey"
           }
         ]
       }
@@ -1394,20 +1491,101 @@ export const IDL: Balex = {
             "type": "publicKey"
           },
           {
-            "name": "debts",
-            "type": {
-              "array": [
-                {
-                  "defined": "Debt"
-                },
-                256
-              ]
-            }
+            "name": "pendingAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "riskAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "feeAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "guardian",
+            "type": "publicKey"
+          },
+          {
+            "name": "nextDebtId",
+            "type": "u64"
+          },
+          {
+            "name": "openDebtsCnt",
+            "type": "u64"
+          },
+          {
+            "name": "minOrderSize",
+            "type": "u64"
+          },
+          {
+            "name": "maxInterestRate",
+            "type": "u64"
+          },
+          {
+            "name": "maxConfidenceBps",
+            "type": "u64"
+          },
+          {
+            "name": "maxPriceStalenessSlots",
+            "type": "u64"
+          },
+          {
+            "name": "feeBalance",
+            "type": "u64"
+          },
+          {
+            "name": "totalFees",
+            "type": "u64"
+          },
+          {
+            "name": "totalInterestPaid",
+            "type": "u64"
+          },
+          {
+            "name": "totalBadDebt",
+            "type": "u64"
+          },
+          {
+            "name": "insuranceBalance",
+            "type": "u64"
+          },
+          {
+            "name": "totalInsurancePaid",
+            "type": "u64"
+          },
+          {
+            "name": "crankFee",
+            "type": "u64"
+          },
+          {
+            "name": "crankFeeBudget",
+            "type": "u64"
+          },
+          {
+            "name": "loanTerm",
+            "type": "u64"
+          },
+          {
+            "name": "gracePeriod",
+            "type": "u64"
+          },
+          {
+            "name": "liquidationAuctionDuration",
+            "type": "u64"
           },
           {
             "name": "overCollateralPercent",
             "type": "u8"
           },
+          {
+            "name": "liquidationBonusPercent",
+            "type": "u8"
+          },
+          {
+            "name": "closeFactorPercent",
+            "type": "u8"
+          },
           {
             "name": "signerBump",
             "type": "u8"
@@ -1419,12 +1597,25 @@ export const IDL: Balex = {
             }
           },
           {
-            "name": "padding",
+            "name": "baseDecimals",
+            "type": "u8"
+          },
+          {
+            "name": "quoteDecimals",
+            "type": "u8"
+          },
+          {
+            "name": "protocolFeePercent",
+            "type": "u8"
+          },
+          {
+            "name": "insurancePercent",
+            "type": "u8"
+          },
+          {
+            "name": "status",
             "type": {
-              "array": [
-                "u8",
-                5
-              ]
+              "defined": "MarketStatus"
             }
           }
         ]
@@ -1460,39 +1651,40 @@ export const IDL: Balex = {
             "type": "u64"
           },
           {
-            "name": "openOrders",
-            "type": {
-              "array": [
-                "u128",
-                16
-              ]
-            }
+            "name": "borrowQty",
+            "type": "u64"
           },
           {
-            "name": "openDebts",
-            "type": {
-              "array": [
-                "u16",
-                16
-              ]
-            }
+            "name": "borrowLiquidQty",
+            "type": "u64"
+          },
+          {
+            "name": "borrowRateSum",
+            "type": "u128"
+          },
+          {
+            "name": "borrowRateTimeSum",
+            "type": "u128"
+          },
+          {
+            "name": "liquidationAuctionStart",
+            "type": "i64"
           },
           {
             "name": "openOrdersCnt",
-            "type": "u8"
+            "type": "u16"
           },
           {
             "name": "openDebtsCnt",
-            "type": "u8"
+            "type": "u16"
           },
           {
-            "name": "padding",
-            "type": {
-              "array": [
-                "u8",
-                6
-              ]
-            }
+            "name": "openOrdersCapacity",
+            "type": "u16"
+          },
+          {
+            "name": "openDebtsCapacity",
+            "type": "u16"
           }
         ]
       }
@@ -1500,50 +1692,3570 @@ export const IDL: Balex = {
   ],
   "types": [
     {
-      "name": "Debt",
+      "name": "MarketParams",
       "type": {
         "kind": "struct",
         "fields": [
           {
-            "name": "lender",
-            "type": "publicKey"
+            "name": "overCollateralPercent",
+            "type": "u8"
           },
           {
-            "name": "borrower",
-            "type": "publicKey"
+            "name": "liquidationBonusPercent",
+            "type": "u8"
           },
           {
-            "name": "timestamp",
-            "type": "i64"
+            "name": "closeFactorPercent",
+            "type": "u8"
           },
           {
-            "name": "interestRate",
+            "name": "minOrderSize",
             "type": "u64"
           },
           {
-            "name": "qty",
+            "name": "maxInterestRate",
             "type": "u64"
           },
           {
-            "name": "liquidQty",
+            "name": "maxConfidenceBps",
+            "type": "u64"
+          },
+          {
+            "name": "maxPriceStalenessSlots",
+            "type": "u64"
+          },
+          {
+            "name": "protocolFeePercent",
+            "type": "u8"
+          },
+          {
+            "name": "crankFee",
+            "type": "u64"
+          },
+          {
+            "name": "gracePeriod",
+            "type": "u64"
+          },
+          {
+            "name": "insurancePercent",
+            "type": "u8"
+          },
+          {
+            "name": "liquidationAuctionDuration",
             "type": "u64"
           }
         ]
       }
     },
     {
-      "name": "OracleType",
+      "name": "LegacyUserAccount",
       "type": {
-        "kind": "enum",
-        "variants": [
+        "kind": "struct",
+        "fields": [
           {
-            "name": "Stub"
+            "name": "owner",
+            "type": "publicKey"
           },
           {
-            "name": "Pyth"
-          }
-        ]
-      }
+            "name": "baseFree",
+            "type": "u64"
+          },
+          {
+            "name": "baseLocked",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenLend",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenBorrow",
+            "type": "u64"
+          },
+          {
+            "name": "quoteTotal",
+            "type": "u64"
+          },
+          {
+            "name": "openOrders",
+            "type": {
+              "array": [
+                "u128",
+                16
+              ]
+            }
+          },
+          {
+            "name": "openDebts",
+            "type": {
+              "array": [
+                "u16",
+                16
+              ]
+            }
+          },
+          {
+            "name": "openOrdersCnt",
+            "type": "u8"
+          },
+          {
+            "name": "openDebtsCnt",
+            "type": "u8"
+          },
+          {
+            "name": "padding",
+            "type": {
+              "array": [
+                "u8",
+                6
+              ]
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "OracleType",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Stub"
+          },
+          {
+            "name": "Pyth"
+          }
+        ]
+      }
+    },
+    {
+      "name": "MarketStatus",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Active"
+          },
+          {
+            "name": "ReduceOnly"
+          },
+          {
+            "name": "Paused"
+          }
+        ]
+      }
+    },
+    {
+      "name": "OrderType",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Limit"
+          },
+          {
+            "name": "PostOnly"
+          },
+          {
+            "name": "ImmediateOrCancel"
+          },
+          {
+            "name": "FillOrKill"
+          }
+        ]
+      }
+    },
+    {
+      "name": "SelfTradeBehavior",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "DecrementTake"
+          },
+          {
+            "name": "CancelProvide"
+          },
+          {
+            "name": "AbortTransaction"
+          }
+        ]
+      }
+    }
+  ],
+  "events": [
+    {
+      "name": "OrderPlaced",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "orderId",
+          "type": "u128",
+          "index": false
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "side",
+          "type": "u8",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "OrderCancelled",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "orderId",
+          "type": "u128",
+          "index": false
+        },
+        {
+          "name": "side",
+          "type": "u8",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtCreated",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "maturity",
+          "type": "i64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtSettled",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "protocolFee",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "remainingQty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtLiquidated",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "liquidator",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "protocolFee",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "overdue",
+          "type": "bool",
+          "index": false
+        },
+        {
+          "name": "bonusBps",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtWrittenOff",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "recovered",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "loss",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "BadDebtResolved",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "liquidator",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "totalDebt",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "seizedQuote",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "recovered",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "insuranceUsed",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "shortfall",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "InsuranceDeposit",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "depositor",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "insuranceBalance",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "InsuranceRetired",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "admin",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "Deposit",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "mint",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "Withdraw",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "mint",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    }
+  ],
+  "errors": [
+    {
+      "code": 300,
+      "name": "InvalidOrderSide",
+      "msg": "Invalid order side, should be 0 (bid) or 1 (ask)"
+    },
+    {
+      "code": 301,
+      "name": "InvalidVault",
+      "msg": "Vault address is not base nor quote vault of the market"
+    },
+    {
+      "code": 302,
+      "name": "InsufficientBaseFree",
+      "msg": "Insufficient free base balance"
+    },
+    {
+      "code": 303,
+      "name": "BorrowLimitExceeded",
+      "msg": "Requested quantity is more than maximum allowed borrow"
+    },
+    {
+      "code": 304,
+      "name": "WithdrawLimitExceeded",
+      "msg": "Requested quantity is more than maximum allowed withdrawal"
+    },
+    {
+      "code": 305,
+      "name": "OpenOrdersLimitReached",
+      "msg": "Max open orders reached"
+    },
+    {
+      "code": 306,
+      "name": "OpenDebtsLimitReached",
+      "msg": "Max open debts reached"
+    },
+    {
+      "code": 307,
+      "name": "OrderNotFound",
+      "msg": "Order is not in user open orders"
+    },
+    {
+      "code": 308,
+      "name": "DebtNotFound",
+      "msg": "Debt is not in user open debts"
+    },
+    {
+      "code": 309,
+      "name": "DebtAccountMissing",
+      "msg": "Debt account is not provided"
+    },
+    {
+      "code": 310,
+      "name": "InvalidDebtAccount",
+      "msg": "Debt account doesn't match the debt"
+    },
+    {
+      "code": 311,
+      "name": "DebtSettled",
+      "msg": "Debt is already settled"
+    },
+    {
+      "code": 312,
+      "name": "OrderSummaryMissing",
+      "msg": "Order summary is missing from event queue register"
+    },
+    {
+      "code": 313,
+      "name": "RiskyOrderNotBid",
+      "msg": "Only bid orders can be cancelled as risky"
+    },
+    {
+      "code": 314,
+      "name": "UserHealthy",
+      "msg": "User is in good shape with health factor >= 100"
+    },
+    {
+      "code": 315,
+      "name": "BorrowerHasOpenBids",
+      "msg": "Borrower has open borrow orders, cancel them first"
+    },
+    {
+      "code": 316,
+      "name": "DebtListLengthMismatch",
+      "msg": "Debt id list size and debt amount list size are not equal"
+    },
+    {
+      "code": 317,
+      "name": "DebtBorrowerMismatch",
+      "msg": "Borrower is not borrower of this debt"
+    },
+    {
+      "code": 318,
+      "name": "LenderAccountMissing",
+      "msg": "Lender user account of debt is not provided"
+    },
+    {
+      "code": 319,
+      "name": "UserAccountMissing",
+      "msg": "User account of event is not provided"
+    },
+    {
+      "code": 320,
+      "name": "CloseFactorExceeded",
+      "msg": "Liquidation amount is more than close factor allows"
+    },
+    {
+      "code": 321,
+      "name": "InsufficientCollateral",
+      "msg": "Borrower doesn't have enough quote to pay for liquidation"
+    },
+    {
+      "code": 322,
+      "name": "HealthStillTooLow",
+      "msg": "Liquidation should bring health factor to >= 100"
+    },
+    {
+      "code": 323,
+      "name": "OracleUnavailable",
+      "msg": "Price oracle is unavailable"
+    },
+    {
+      "code": 324,
+      "name": "NoEventsConsumed",
+      "msg": "No event could be consumed"
+    },
+    {
+      "code": 325,
+      "name": "OrderbookError",
+      "msg": "Orderbook instruction failed"
+    },
+    {
+      "code": 326,
+      "name": "InvalidMarketParams",
+      "msg": "Invalid market params"
+    },
+    {
+      "code": 327,
+      "name": "OrderSizeTooSmall",
+      "msg": "Order size is less than market minimum"
+    },
+    {
+      "code": 328,
+      "name": "InterestRateTooHigh",
+      "msg": "Interest rate is more than market maximum"
+    },
+    {
+      "code": 329,
+      "name": "MathOverflow",
+      "msg": "Math overflow"
+    },
+    {
+      "code": 330,
+      "name": "OraclePriceNotTrading",
+      "msg": "Oracle price status is not trading"
+    },
+    {
+      "code": 331,
+      "name": "OraclePriceStale",
+      "msg": "Oracle price is stale"
+    },
+    {
+      "code": 332,
+      "name": "OracleConfidenceTooWide",
+      "msg": "Oracle price confidence interval is too wide"
+    },
+    {
+      "code": 333,
+      "name": "InvalidUserAccountCapacity",
+      "msg": "Invalid open orders or open debts capacity of user account"
+    },
+    {
+      "code": 334,
+      "name": "InvalidPriceOracle",
+      "msg": "Price oracle is not the price oracle of the market"
+    },
+    {
+      "code": 335,
+      "name": "InvalidTokenAccount",
+      "msg": "Token account mint or owner is not as expected"
+    },
+    {
+      "code": 336,
+      "name": "InvalidUserAccount",
+      "msg": "User account doesn't belong to the market"
+    },
+    {
+      "code": 337,
+      "name": "InvalidLenderAccount",
+      "msg": "Lender account doesn't belong to lender of the debt"
+    },
+    {
+      "code": 338,
+      "name": "InvalidRepayAmount",
+      "msg": "Repay amount should be more than zero"
+    },
+    {
+      "code": 339,
+      "name": "UserAccountNotEmpty",
+      "msg": "User account still has balance, open orders or open debts"
+    },
+    {
+      "code": 340,
+      "name": "MarketNotEmpty",
+      "msg": "Market still has open debts or balance in vaults"
+    },
+    {
+      "code": 341,
+      "name": "EventQueueNotEmpty",
+      "msg": "Event queue of the market is not drained"
+    },
+    {
+      "code": 342,
+      "name": "InvalidExpiryTimestamp",
+      "msg": "Expiry timestamp should be zero or in the future"
+    },
+    {
+      "code": 343,
+      "name": "NoExpiredOrders",
+      "msg": "User has no expired order to purge"
+    },
+    {
+      "code": 344,
+      "name": "PostOnlyWouldMatch",
+      "msg": "Post only order would match"
+    },
+    {
+      "code": 345,
+      "name": "OrderNotFilled",
+      "msg": "Fill or kill order is not filled completely"
+    },
+    {
+      "code": 346,
+      "name": "DuplicateClientOrderId",
+      "msg": "Client order id is used by another open order"
+    },
+    {
+      "code": 347,
+      "name": "BorrowerNotInsolvent",
+      "msg": "Borrower can pay its debts, liquidate it instead"
+    },
+    {
+      "code": 348,
+      "name": "MarketNotActive",
+      "msg": "Market is reduce-only or paused"
+    },
+    {
+      "code": 349,
+      "name": "MarketPaused",
+      "msg": "Market is paused"
+    },
+    {
+      "code": 350,
+      "name": "Unauthorized",
+      "msg": "Signer is not allowed to do this on the market"
+    },
+    {
+      "code": 351,
+      "name": "DebtNotSettled",
+      "msg": "Debt is not settled yet"
+    },
+    {
+      "code": 352,
+      "name": "InvalidDebtPayer",
+      "msg": "Account is not payer of the debt account"
+    },
+    {
+      "code": 353,
+      "name": "RepayAmountTooSmall",
+      "msg": "Partial repay should be at least min order size"
+    },
+    {
+      "code": 354,
+      "name": "MarketNotPaused",
+      "msg": "Market should be paused first"
+    },
+    {
+      "code": 355,
+      "name": "UserAccountNotMigrated",
+      "msg": "User account has legacy layout, migrate it first"
+    },
+    {
+      "code": 356,
+      "name": "UserAccountNotLegacy",
+      "msg": "User account doesn't have legacy layout"
+    },
+    {
+      "code": 357,
+      "name": "LegacyDebtsOpen",
+      "msg": "Legacy open debts can't be migrated"
+    }
+  ]
+};
+
+export const IDL: Balex = {
+  "version": "0.1.0",
+  "name": "balex",
+  "instructions": [
+    {
+      "name": "initializeMarket",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseMint",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "quoteMint",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "quoteVault",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "signerBump",
+          "type": "u8"
+        },
+        {
+          "name": "oracleType",
+          "type": {
+            "defined": "OracleType"
+          }
+        },
+        {
+          "name": "loanTerm",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "updateMarketParams",
+      "accounts": [
+        {
+          "name": "riskAdmin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "params",
+          "type": {
+            "defined": "MarketParams"
+          }
+        }
+      ]
+    },
+    {
+      "name": "setMarketStatus",
+      "accounts": [
+        {
+          "name": "authority",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "status",
+          "type": {
+            "defined": "MarketStatus"
+          }
+        }
+      ]
+    },
+    {
+      "name": "setRoles",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "riskAdmin",
+          "type": "publicKey"
+        },
+        {
+          "name": "feeAdmin",
+          "type": "publicKey"
+        },
+        {
+          "name": "guardian",
+          "type": "publicKey"
+        }
+      ]
+    },
+    {
+      "name": "proposeAdmin",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "newAdmin",
+          "type": "publicKey"
+        }
+      ]
+    },
+    {
+      "name": "acceptAdmin",
+      "accounts": [
+        {
+          "name": "pendingAdmin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "initializeAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        }
+      ]
+    },
+    {
+      "name": "resizeUserAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "openOrdersCapacity",
+          "type": "u16"
+        },
+        {
+          "name": "openDebtsCapacity",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "migrateUserAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        }
+      ]
+    },
+    {
+      "name": "deposit",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenSource",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "setStubPrice",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "stubPrice",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "price",
+          "type": "i64"
+        },
+        {
+          "name": "conf",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "newOrder",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "sideNum",
+          "type": "u8"
+        },
+        {
+          "name": "interestRate",
+          "type": "u64"
+        },
+        {
+          "name": "qty",
+          "type": "u64"
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64"
+        },
+        {
+          "name": "orderType",
+          "type": {
+            "defined": "OrderType"
+          }
+        },
+        {
+          "name": "selfTradeBehavior",
+          "type": {
+            "defined": "SelfTradeBehavior"
+          }
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "cancelMyOrder",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "orderId",
+          "type": "u128"
+        }
+      ]
+    },
+    {
+      "name": "cancelByClientOrderId",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "cancelAllOrders",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "sideNum",
+          "type": {
+            "option": "u8"
+          }
+        }
+      ]
+    },
+    {
+      "name": "replaceOrder",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "orderId",
+          "type": "u128"
+        },
+        {
+          "name": "interestRate",
+          "type": "u64"
+        },
+        {
+          "name": "qty",
+          "type": "u64"
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64"
+        },
+        {
+          "name": "orderType",
+          "type": {
+            "defined": "OrderType"
+          }
+        },
+        {
+          "name": "selfTradeBehavior",
+          "type": {
+            "defined": "SelfTradeBehavior"
+          }
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "cancelRiskyOrder",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "orderId",
+          "type": "u128"
+        }
+      ]
+    },
+    {
+      "name": "purgeExpiredOrders",
+      "accounts": [
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "maxOrders",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "consumeOrderEvents",
+      "accounts": [
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "payer",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "rewardTarget",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "systemProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "maxIterations",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "withdraw",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "vault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenDest",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "settleDebt",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "lenderAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "debt",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        },
+        {
+          "name": "debtId",
+          "type": "u64"
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "repayFromWallet",
+      "accounts": [
+        {
+          "name": "payer",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "tokenBaseSrc",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "lenderAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "debt",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "debtId",
+          "type": "u64"
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "liquidateDebts",
+      "accounts": [
+        {
+          "name": "liquidator",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "quoteVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenBaseSrc",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenQuoteDest",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "debtsId",
+          "type": {
+            "vec": "u64"
+          }
+        },
+        {
+          "name": "debtsAmount",
+          "type": {
+            "vec": "u64"
+          }
+        }
+      ]
+    },
+    {
+      "name": "closeDebt",
+      "accounts": [
+        {
+          "name": "debt",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "payer",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "updateLiquidationAuction",
+      "accounts": [
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "resolveBadDebt",
+      "accounts": [
+        {
+          "name": "liquidator",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "borrowerAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "quoteVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenBaseSrc",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenQuoteDest",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "priceOracle",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "orderDummy",
+      "accounts": [
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "claimFees",
+      "accounts": [
+        {
+          "name": "feeAdmin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenDest",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "depositInsurance",
+      "accounts": [
+        {
+          "name": "depositor",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenSrc",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "retireInsurance",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": false,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenDest",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeUserAccount",
+      "accounts": [
+        {
+          "name": "owner",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "userAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "market",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": [
+        {
+          "name": "bump",
+          "type": "u8"
+        }
+      ]
+    },
+    {
+      "name": "closeMarket",
+      "accounts": [
+        {
+          "name": "admin",
+          "isMut": true,
+          "isSigner": true
+        },
+        {
+          "name": "market",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "marketSigner",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "baseVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "quoteVault",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "eventQueue",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "orderbook",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "asks",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "bids",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "tokenProgram",
+          "isMut": false,
+          "isSigner": false
+        }
+      ],
+      "args": []
+    }
+  ],
+  "accounts": [
+    {
+      "name": "stubPrice",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "price",
+            "type": "i64"
+          },
+          {
+            "name": "conf",
+            "type": "u64"
+          },
+          {
+            "name": "authority",
+            "type": "publicKey"
+          }
+        ]
+      }
+    },
+    {
+      "name": "debt",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "publicKey"
+          },
+          {
+            "name": "lender",
+            "type": "publicKey"
+          },
+          {
+            "name": "borrower",
+            "type": "publicKey"
+          },
+          {
+            "name": "id",
+            "type": "u64"
+          },
+          {
+            "name": "timestamp",
+            "type": "i64"
+          },
+          {
+            "name": "interestRate",
+            "type": "u64"
+          },
+          {
+            "name": "qty",
+            "type": "u64"
+          },
+          {
+            "name": "liquidQty",
+            "type": "u64"
+          },
+          {
+            "name": "accruedInterest",
+            "type": "u64"
+          },
+          {
+            "name": "maturity",
+            "type": "i64"
+          },
+          {
+            "name": "payer",
+            "type": "publicKey"
+          }
+        ]
+      }
+    },
+    {
+      "name": "lexMarket",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "baseMint",
+            "type": "publicKey"
+          },
+          {
+            "name": "quoteMint",
+            "type": "publicKey"
+          },
+          {
+            "name": "baseVault",
+            "type": "publicKey"
+          },
+          {
+            "name": "quoteVault",
+            "type": "publicKey"
+          },
+          {
+            "name": "priceOracle",
+            "type": "publicKey"
+          },
+          {
+            "name": "orderbook",
+            "type": "publicKey"
+          },
+          {
+            "name": "admin",
+            "type": "publicKey"
+          },
+          {
+            "name": "pendingAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "riskAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "feeAdmin",
+            "type": "publicKey"
+          },
+          {
+            "name": "guardian",
+            "type": "publicKey"
+          },
+          {
+            "name": "nextDebtId",
+            "type": "u64"
+          },
+          {
+            "name": "openDebtsCnt",
+            "type": "u64"
+          },
+          {
+            "name": "minOrderSize",
+            "type": "u64"
+          },
+          {
+            "name": "maxInterestRate",
+            "type": "u64"
+          },
+          {
+            "name": "maxConfidenceBps",
+            "type": "u64"
+          },
+          {
+            "name": "maxPriceStalenessSlots",
+            "type": "u64"
+          },
+          {
+            "name": "feeBalance",
+            "type": "u64"
+          },
+          {
+            "name": "totalFees",
+            "type": "u64"
+          },
+          {
+            "name": "totalInterestPaid",
+            "type": "u64"
+          },
+          {
+            "name": "totalBadDebt",
+            "type": "u64"
+          },
+          {
+            "name": "insuranceBalance",
+            "type": "u64"
+          },
+          {
+            "name": "totalInsurancePaid",
+            "type": "u64"
+          },
+          {
+            "name": "crankFee",
+            "type": "u64"
+          },
+          {
+            "name": "crankFeeBudget",
+            "type": "u64"
+          },
+          {
+            "name": "loanTerm",
+            "type": "u64"
+          },
+          {
+            "name": "gracePeriod",
+            "type": "u64"
+          },
+          {
+            "name": "liquidationAuctionDuration",
+            "type": "u64"
+          },
+          {
+            "name": "overCollateralPercent",
+            "type": "u8"
+          },
+          {
+            "name": "liquidationBonusPercent",
+            "type": "u8"
+          },
+          {
+            "name": "closeFactorPercent",
+            "type": "u8"
+          },
+          {
+            "name": "signerBump",
+            "type": "u8"
+          },
+          {
+            "name": "oracleType",
+            "type": {
+              "defined": "OracleType"
+            }
+          },
+          {
+            "name": "baseDecimals",
+            "type": "u8"
+          },
+          {
+            "name": "quoteDecimals",
+            "type": "u8"
+          },
+          {
+            "name": "protocolFeePercent",
+            "type": "u8"
+          },
+          {
+            "name": "insurancePercent",
+            "type": "u8"
+          },
+          {
+            "name": "status",
+            "type": {
+              "defined": "MarketStatus"
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "userAccount",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "owner",
+            "type": "publicKey"
+          },
+          {
+            "name": "baseFree",
+            "type": "u64"
+          },
+          {
+            "name": "baseLocked",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenLend",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenBorrow",
+            "type": "u64"
+          },
+          {
+            "name": "quoteTotal",
+            "type": "u64"
+          },
+          {
+            "name": "borrowQty",
+            "type": "u64"
+          },
+          {
+            "name": "borrowLiquidQty",
+            "type": "u64"
+          },
+          {
+            "name": "borrowRateSum",
+            "type": "u128"
+          },
+          {
+            "name": "borrowRateTimeSum",
+            "type": "u128"
+          },
+          {
+            "name": "liquidationAuctionStart",
+            "type": "i64"
+          },
+          {
+            "name": "openOrdersCnt",
+            "type": "u16"
+          },
+          {
+            "name": "openDebtsCnt",
+            "type": "u16"
+          },
+          {
+            "name": "openOrdersCapacity",
+            "type": "u16"
+          },
+          {
+            "name": "openDebtsCapacity",
+            "type": "u16"
+          }
+        ]
+      }
+    }
+  ],
+  "types": [
+    {
+      "name": "MarketParams",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "overCollateralPercent",
+            "type": "u8"
+          },
+          {
+            "name": "liquidationBonusPercent",
+            "type": "u8"
+          },
+          {
+            "name": "closeFactorPercent",
+            "type": "u8"
+          },
+          {
+            "name": "minOrderSize",
+            "type": "u64"
+          },
+          {
+            "name": "maxInterestRate",
+            "type": "u64"
+          },
+          {
+            "name": "maxConfidenceBps",
+            "type": "u64"
+          },
+          {
+            "name": "maxPriceStalenessSlots",
+            "type": "u64"
+          },
+          {
+            "name": "protocolFeePercent",
+            "type": "u8"
+          },
+          {
+            "name": "crankFee",
+            "type": "u64"
+          },
+          {
+            "name": "gracePeriod",
+            "type": "u64"
+          },
+          {
+            "name": "insurancePercent",
+            "type": "u8"
+          },
+          {
+            "name": "liquidationAuctionDuration",
+            "type": "u64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "LegacyUserAccount",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "owner",
+            "type": "publicKey"
+          },
+          {
+            "name": "baseFree",
+            "type": "u64"
+          },
+          {
+            "name": "baseLocked",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenLend",
+            "type": "u64"
+          },
+          {
+            "name": "baseOpenBorrow",
+            "type": "u64"
+          },
+          {
+            "name": "quoteTotal",
+            "type": "u64"
+          },
+          {
+            "name": "openOrders",
+            "type": {
+              "array": [
+                "u128",
+                16
+              ]
+            }
+          },
+          {
+            "name": "openDebts",
+            "type": {
+              "array": [
+                "u16",
+                16
+              ]
+            }
+          },
+          {
+            "name": "openOrdersCnt",
+            "type": "u8"
+          },
+          {
+            "name": "openDebtsCnt",
+            "type": "u8"
+          },
+          {
+            "name": "padding",
+            "type": {
+              "array": [
+                "u8",
+                6
+              ]
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "OracleType",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Stub"
+          },
+          {
+            "name": "Pyth"
+          }
+        ]
+      }
+    },
+    {
+      "name": "MarketStatus",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Active"
+          },
+          {
+            "name": "ReduceOnly"
+          },
+          {
+            "name": "Paused"
+          }
+        ]
+      }
+    },
+    {
+      "name": "OrderType",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "Limit"
+          },
+          {
+            "name": "PostOnly"
+          },
+          {
+            "name": "ImmediateOrCancel"
+          },
+          {
+            "name": "FillOrKill"
+          }
+        ]
+      }
+    },
+    {
+      "name": "SelfTradeBehavior",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "DecrementTake"
+          },
+          {
+            "name": "CancelProvide"
+          },
+          {
+            "name": "AbortTransaction"
+          }
+        ]
+      }
+    }
+  ],
+  "events": [
+    {
+      "name": "OrderPlaced",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "orderId",
+          "type": "u128",
+          "index": false
+        },
+        {
+          "name": "clientOrderId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "side",
+          "type": "u8",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "expiryTimestamp",
+          "type": "i64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "OrderCancelled",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "orderId",
+          "type": "u128",
+          "index": false
+        },
+        {
+          "name": "side",
+          "type": "u8",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtCreated",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "qty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "maturity",
+          "type": "i64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtSettled",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "protocolFee",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "remainingQty",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtLiquidated",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "liquidator",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "protocolFee",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "overdue",
+          "type": "bool",
+          "index": false
+        },
+        {
+          "name": "bonusBps",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "DebtWrittenOff",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "debtId",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "lender",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "recovered",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "loss",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "interestRate",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "BadDebtResolved",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "borrower",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "liquidator",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "totalDebt",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "seizedQuote",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "recovered",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "insuranceUsed",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "shortfall",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "InsuranceDeposit",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "depositor",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "insuranceBalance",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "InsuranceRetired",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "admin",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "Deposit",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "mint",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    },
+    {
+      "name": "Withdraw",
+      "fields": [
+        {
+          "name": "market",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "user",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "mint",
+          "type": "publicKey",
+          "index": false
+        },
+        {
+          "name": "amount",
+          "type": "u64",
+          "index": false
+        },
+        {
+          "name": "timestamp",
+          "type": "i64",
+          "index": false
+        }
+      ]
+    }
+  ],
+  "errors": [
+    {
+      "code": 300,
+      "name": "InvalidOrderSide",
+      "msg": "Invalid order side, should be 0 (bid) or 1 (ask)"
+    },
+    {
+      "code": 301,
+      "name": "InvalidVault",
+      "msg": "Vault address is not base nor quote vault of the market"
+    },
+    {
+      "code": 302,
+      "name": "InsufficientBaseFree",
+      "msg": "Insufficient free base balance"
+    },
+    {
+      "code": 303,
+      "name": "BorrowLimitExceeded",
+      "msg": "Requested quantity is more than maximum allowed borrow"
+    },
+    {
+      "code": 304,
+      "name": "WithdrawLimitExceeded",
+      "msg": "Requested quantity is more than maximum allowed withdrawal"
+    },
+    {
+      "code": 305,
+      "name": "OpenOrdersLimitReached",
+      "msg": "Max open orders reached"
+    },
+    {
+      "code": 306,
+      "name": "OpenDebtsLimitReached",
+      "msg": "Max open debts reached"
+    },
+    {
+      "code": 307,
+      "name": "OrderNotFound",
+      "msg": "Order is not in user open orders"
+    },
+    {
+      "code": 308,
+      "name": "DebtNotFound",
+      "msg": "Debt is not in user open debts"
+    },
+    {
+      "code": 309,
+      "name": "DebtAccountMissing",
+      "msg": "Debt account is not provided"
+    },
+    {
+      "code": 310,
+      "name": "InvalidDebtAccount",
+      "msg": "Debt account doesn't match the debt"
+    },
+    {
+      "code": 311,
+      "name": "DebtSettled",
+      "msg": "Debt is already settled"
+    },
+    {
+      "code": 312,
+      "name": "OrderSummaryMissing",
+      "msg": "Order summary is missing from event queue register"
+    },
+    {
+      "code": 313,
+      "name": "RiskyOrderNotBid",
+      "msg": "Only bid orders can be cancelled as risky"
+    },
+    {
+      "code": 314,
+      "name": "UserHealthy",
+      "msg": "User is in good shape with health factor >= 100"
+    },
+    {
+      "code": 315,
+      "name": "BorrowerHasOpenBids",
+      "msg": "Borrower has open borrow orders, cancel them first"
+    },
+    {
+      "code": 316,
+      "name": "DebtListLengthMismatch",
+      "msg": "Debt id list size and debt amount list size are not equal"
+    },
+    {
+      "code": 317,
+      "name": "DebtBorrowerMismatch",
+      "msg": "Borrower is not borrower of this debt"
+    },
+    {
+      "code": 318,
+      "name": "LenderAccountMissing",
+      "msg": "Lender user account of debt is not provided"
+    },
+    {
+      "code": 319,
+      "name": "UserAccountMissing",
+      "msg": "User account of event is not provided"
+    },
+    {
+      "code": 320,
+      "name": "CloseFactorExceeded",
+      "msg": "Liquidation amount is more than close factor allows"
+    },
+    {
+      "code": 321,
+      "name": "InsufficientCollateral",
+      "msg": "Borrower doesn't have enough quote to pay for liquidation"
+    },
+    {
+      "code": 322,
+      "name": "HealthStillTooLow",
+      "msg": "Liquidation should bring health factor to >= 100"
+    },
+    {
+      "code": 323,
+      "name": "OracleUnavailable",
+      "msg": "Price oracle is unavailable"
+    },
+    {
+      "code": 324,
+      "name": "NoEventsConsumed",
+      "msg": "No event could be consumed"
+    },
+    {
+      "code": 325,
+      "name": "OrderbookError",
+      "msg": "Orderbook instruction failed"
+    },
+    {
+      "code": 326,
+      "name": "InvalidMarketParams",
+      "msg": "Invalid market params"
+    },
+    {
+      "code": 327,
+      "name": "OrderSizeTooSmall",
+      "msg": "Order size is less than market minimum"
+    },
+    {
+      "code": 328,
+      "name": "InterestRateTooHigh",
+      "msg": "Interest rate is more than market maximum"
+    },
+    {
+      "code": 329,
+      "name": "MathOverflow",
+      "msg": "Math overflow"
+    },
+    {
+      "code": 330,
+      "name": "OraclePriceNotTrading",
+      "msg": "Oracle price status is not trading"
+    },
+    {
+      "code": 331,
+      "name": "OraclePriceStale",
+      "msg": "Oracle price is stale"
+    },
+    {
+      "code": 332,
+      "name": "OracleConfidenceTooWide",
+      "msg": "Oracle price confidence interval is too wide"
+    },
+    {
+      "code": 333,
+      "name": "InvalidUserAccountCapacity",
+      "msg": "Invalid open orders or open debts capacity of user account"
+    },
+    {
+      "code": 334,
+      "name": "InvalidPriceOracle",
+      "msg": "Price oracle is not the price oracle of the market"
+    },
+    {
+      "code": 335,
+      "name": "InvalidTokenAccount",
+      "msg": "Token account mint or owner is not as expected"
+    },
+    {
+      "code": 336,
+      "name": "InvalidUserAccount",
+      "msg": "User account doesn't belong to the market"
+    },
+    {
+      "code": 337,
+      "name": "InvalidLenderAccount",
+      "msg": "Lender account doesn't belong to lender of the debt"
+    },
+    {
+      "code": 338,
+      "name": "InvalidRepayAmount",
+      "msg": "Repay amount should be more than zero"
+    },
+    {
+      "code": 339,
+      "name": "UserAccountNotEmpty",
+      "msg": "User account still has balance, open orders or open debts"
+    },
+    {
+      "code": 340,
+      "name": "MarketNotEmpty",
+      "msg": "Market still has open debts or balance in vaults"
+    },
+    {
+      "code": 341,
+      "name": "EventQueueNotEmpty",
+      "msg": "Event queue of the market is not drained"
+    },
+    {
+      "code": 342,
+      "name": "InvalidExpiryTimestamp",
+      "msg": "Expiry timestamp should be zero or in the future"
+    },
+    {
+      "code": 343,
+      "name": "NoExpiredOrders",
+      "msg": "User has no expired order to purge"
+    },
+    {
+      "code": 344,
+      "name": "PostOnlyWouldMatch",
+      "msg": "Post only order would match"
+    },
+    {
+      "code": 345,
+      "name": "OrderNotFilled",
+      "msg": "Fill or kill order is not filled completely"
+    },
+    {
+      "code": 346,
+      "name": "DuplicateClientOrderId",
+      "msg": "Client order id is used by another open order"
+    },
+    {
+      "code": 347,
+      "name": "BorrowerNotInsolvent",
+      "msg": "Borrower can pay its debts, liquidate it instead"
+    },
+    {
+      "code": 348,
+      "name": "MarketNotActive",
+      "msg": "Market is reduce-only or paused"
+    },
+    {
+      "code": 349,
+      "name": "MarketPaused",
+      "msg": "Market is paused"
+    },
+    {
+      "code": 350,
+      "name": "Unauthorized",
+      "msg": "Signer is not allowed to do this on the market"
+    },
+    {
+      "code": 351,
+      "name": "DebtNotSettled",
+      "msg": "Debt is not settled yet"
+    },
+    {
+      "code": 352,
+      "name": "InvalidDebtPayer",
+      "msg": "Account is not payer of the debt account"
+    },
+    {
+      "code": 353,
+      "name": "RepayAmountTooSmall",
+      "msg": "Partial repay should be at least min order size"
+    },
+    {
+      "code": 354,
+      "name": "MarketNotPaused",
+      "msg": "Market should be paused first"
+    },
+    {
+      "code": 355,
+      "name": "UserAccountNotMigrated",
+      "msg": "User account has legacy layout, migrate it first"
+    },
+    {
+      "code": 356,
+      "name": "UserAccountNotLegacy",
+      "msg": "User account doesn't have legacy layout"
+    },
+    {
+      "code": 357,
+      "name": "LegacyDebtsOpen",
+      "msg": "Legacy open debts can't be migrated"
     }
   ]
 };
@@ -77,32 +77,54 @@ export async function getMarketAccounts(program: Program<Balex>): Promise<[Publi
     return [orderbook, orderbookData.eventQueue, orderbookData.asks, orderbookData.bids];
 }
 
-export type DebtType = {
-    borrower: PublicKey,
-    lender: PublicKey,
-    interestRate: anchor.BN,
-    liquidQty: anchor.BN,
-    qty: anchor.BN,
-    timestamp: anchor.BN
+// Open orders are stored after the user account fields (discriminator + 136 bytes), each one is
+// order id (u128), expiry timestamp (i64) and client order id (u64)
+const USER_ACCOUNT_TAIL_OFFSET = 8 + 136;
+const OPEN_ORDER_LEN = 32;
+
+export async function getOpenOrderIds(userPub: PublicKey, userAccount: IdlAccounts<Balex>['userAccount'], program: Program<Balex>): Promise<anchor.BN[]> {
+    const data = (await program.provider.connection.getAccountInfo(userPub, 'confirmed')).data;
+
+    let orderIds: anchor.BN[] = [];
+    for (let i = 0; i < userAccount.openOrdersCnt; i++) {
+        const offset = USER_ACCOUNT_TAIL_OFFSET + OPEN_ORDER_LEN * i;
+        orderIds.push(new anchor.BN(data.slice(offset, offset + 16), 'le'));
+    }
+    return orderIds;
+}
+
+export type DebtType = IdlAccounts<Balex>['debt'];
+
+// Debt account is discriminator, market, lender and borrower first
+const DEBT_MARKET_OFFSET = 8;
+const DEBT_LENDER_OFFSET = 8 + 32;
+const DEBT_BORROWER_OFFSET = 8 + 64;
+
+// Open debts of the user on the market, as lender and as borrower
+export async function getUserDebts(owner: PublicKey, program: Program<Balex>): Promise<DebtType[]> {
+    const marketFilter = { memcmp: { offset: DEBT_MARKET_OFFSET, bytes: lexMarketPubkey.toBase58() } };
+
+    const [lent, borrowed] = await Promise.all([
+        program.account.debt.all([marketFilter, { memcmp: { offset: DEBT_LENDER_OFFSET, bytes: owner.toBase58() } }]),
+        program.account.debt.all([marketFilter, { memcmp: { offset: DEBT_BORROWER_OFFSET, bytes: owner.toBase58() } }]),
+    ]);
+
+    // Settled debts stay with zero qty until their payer closes them
+    return lent.concat(borrowed).map(debt => debt.account).filter(debt => !debt.qty.isZero());
 }
 
+// Same as Debt::get_debt_as_of_now of the program, interest since debt timestamp is rounded half up
 export function getDebtAsOfNow(debt: DebtType): number {
-    let diff_timestamp = Date.now()/1000 - debt.timestamp.toNumber();
-    let nowDebt = debt.qty.toNumber();
-    console.log(diff_timestamp);
-    console.log(nowDebt);
-    nowDebt = nowDebt + diff_timestamp*debt.interestRate.toNumber() / (60*60*100)
-    nowDebt = Math.ceil(nowDebt);
-    return nowDebt - debt.liquidQty.toNumber(); 
+    let diff_timestamp = Math.max(Math.floor(Date.now()/1000) - debt.timestamp.toNumber(), 0);
+    let interest = Math.round(debt.qty.toNumber() * debt.interestRate.toNumber() * diff_timestamp / (60*60*100));
+    return debt.qty.toNumber() + debt.accruedInterest.toNumber() + interest - debt.liquidQty.toNumber();
 }
 
 export async function getTotalDebtToPay(userAccount: IdlAccounts<Balex>['userAccount'], program: Program<Balex>): Promise<number> {
-    let marketData = await program.account.lexMarket.fetch(lexMarketPubkey);
+    let debts = await getUserDebts(userAccount.owner, program);
 
     let totalDebt: number = 0;
-    for (let i = 0; i < userAccount.openDebtsCnt; i++) {
-        let debt_id = userAccount.openDebts[i];
-        let debt: DebtType = marketData.debts[debt_id]
+    for (let debt of debts) {
         if (debt.borrower.equals(userAccount.owner)) {
             totalDebt += getDebtAsOfNow(debt);
         }
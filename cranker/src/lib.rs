@@ -13,17 +13,20 @@ use agnostic_orderbook::state::{
     Event, EventQueue, EventQueueHeader, MarketState, MARKET_STATE_LEN,
 };
 use borsh::BorshDeserialize;
-use balex::instruction::ConsumeOrderEvents as InstructionConsume;
+use balex::instruction::{CloseDebt as InstructionCloseDebt, ConsumeOrderEvents as InstructionConsume};
 use balex::{
-    state::{LexMarket},
+    state::{Debt, LexMarket},
     state::CALLBACK_INFO_LEN,
+    state::get_debt_address,
 };
-use balex::accounts::{ConsumerOrderEvents, RemUserAccount};
+use balex::accounts::{CloseDebt, ConsumerOrderEvents, RemUserAccount};
 
 use error::CrankError;
 use solana_client::{
-    rpc_client::RpcClient
+    rpc_client::RpcClient,
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
 };
+use solana_sdk::signer::Signer;
 use solana_sdk::{
     signature::{Signature},
     system_program
@@ -70,7 +73,9 @@ impl Context {
         let orderbook =
             bytemuck::try_from_bytes::<MarketState>(&orderbook_data[..MARKET_STATE_LEN]).unwrap();
         loop {
-            let res = self.consume_events_iteration(&connection, &orderbook, &market_state, &program);
+            let res = self.consume_events_iteration(&connection, &orderbook, &program);
+            println!("{:#?}", res);
+            let res = self.close_settled_debts(&program);
             println!("{:#?}", res);
             thread::sleep(time::Duration::from_secs(1));
         }
@@ -80,9 +85,13 @@ impl Context {
         &self,
         connection: &RpcClient,
         orderbook: &MarketState,
-        market_state: &LexMarket,
         program: &Program,
     ) -> Result<Signature, ClientError> {
+        // Market is fetched each time since next debt id changes with fills
+        let market_state_data = connection.get_account_data(&self.market)?;
+        let market_state =
+            bytemuck::try_from_bytes::<LexMarket>(&market_state_data[8..]).unwrap();
+
         let mut event_queue_data =
             connection.get_account_data(&Pubkey::new(&orderbook.event_queue))?;
         let event_queue_header =
@@ -94,7 +103,8 @@ impl Context {
             CALLBACK_INFO_LEN as usize,
         );
         let mut user_accounts = Vec::with_capacity(length << 1);
-        for e in event_queue.iter() {
+        let mut fills_cnt: u64 = 0;
+        for (i, e) in event_queue.iter().enumerate() {
             match e {
                 Event::Fill {
                     taker_side: _,
@@ -106,6 +116,9 @@ impl Context {
                 } => {
                     user_accounts.push(maker_callback_info);
                     user_accounts.push(taker_callback_info);
+                    if (i as u64) < MAX_ITERATIONS {
+                        fills_cnt += 1;
+                    }
                 }
                 Event::Out {
                     side: _,
@@ -138,9 +151,18 @@ impl Context {
             market: self.market,
             orderbook: market_state.orderbook,
             event_queue: Pubkey::new(&orderbook.event_queue[..]),
+            payer: self.fee_payer.pubkey(),
+            reward_target: self.reward_target,
             system_program: system_program::ID
         });
 
+        // Each consumed fill creates a debt account with the next debt id
+        for debt_id in market_state.next_debt_id..market_state.next_debt_id + fills_cnt {
+            request = request.accounts(RemUserAccount{
+                user_account: get_debt_address(&self.market, debt_id, &self.program_id).0
+            })
+        }
+
         for user_account in user_accounts {
             let user_pubkey = Pubkey::new(&user_account[..]);
             request = request.accounts(RemUserAccount{
@@ -156,4 +178,32 @@ impl Context {
 
         request.send()
     }
+
+    // Gets back rent of debt accounts paid by fills of this cranker once they are settled
+    pub fn close_settled_debts(&self, program: &Program) -> Result<usize, ClientError> {
+        // Market key is right after the discriminator
+        let debts: Vec<(Pubkey, Debt)> = program.accounts::<Debt>(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 8,
+            bytes: MemcmpEncodedBytes::Base58(self.market.to_string()),
+            encoding: None,
+        })])?;
+
+        let mut closed = 0;
+        for (debt_pub, debt) in debts.iter() {
+            if debt.qty > 0 || debt.payer != self.fee_payer.pubkey() {
+                continue;
+            }
+
+            let mut request = program.request();
+            request = request.accounts(CloseDebt {
+                debt: *debt_pub,
+                payer: debt.payer,
+            });
+            request = request.args(InstructionCloseDebt {});
+            request.send()?;
+            closed += 1;
+        }
+
+        Ok(closed)
+    }
 }
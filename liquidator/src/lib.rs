@@ -13,9 +13,9 @@ use std::str::FromStr;
 use agnostic_orderbook::state::{
     MarketState, MARKET_STATE_LEN, get_side_from_order_id, Side,
 };
-use balex::state::{UserAccount, get_user_health_factor, get_user_total_debt, get_user_health_factor_after_liquid};
-use balex::instruction::{CancelRiskyOrder as CancelRiskyOrderInst, LiquidateDebts as LiquidateDebtsInst};
-use balex::accounts::{CancelRiskyOrder as CancelRiskyOrderAccount, LiquidateDebts as LiquidateDebtsAccount};
+use balex::state::{Debt, UserAccount, get_quote_price, get_user_health_factor, get_user_total_debt, get_user_health_factor_after_liquid, is_user_insolvent};
+use balex::instruction::{CancelRiskyOrder as CancelRiskyOrderInst, LiquidateDebts as LiquidateDebtsInst, ResolveBadDebt as ResolveBadDebtInst, UpdateLiquidationAuction as UpdateLiquidationAuctionInst};
+use balex::accounts::{CancelRiskyOrder as CancelRiskyOrderAccount, LiquidateDebts as LiquidateDebtsAccount, ResolveBadDebt as ResolveBadDebtAccount, UpdateLiquidationAuction as UpdateLiquidationAuctionAccount};
 use balex::accounts::RemUserAccount;
 use balex::{
     state::{LexMarket},
@@ -23,7 +23,8 @@ use balex::{
 
 use error::CrankError;
 use solana_client::{
-    rpc_client::RpcClient
+    rpc_client::RpcClient,
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
 };
 use solana_sdk::signer::Signer;
 use solana_sdk::{
@@ -102,9 +103,21 @@ impl Context {
 
         println!("{}", oracle_account_info.data_len());
 
+        // Use cluster time so debts are calculated the same as the program
+        let slot = connection.get_slot()?;
+        let now = connection.get_block_time(slot)?;
+        let price = get_quote_price(&market_state, &oracle_account_info, slot)?;
+
+        // Debt accounts of the market, market key is right after the discriminator
+        let debts: Vec<(Pubkey, Debt)> = program.accounts::<Debt>(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 8,
+            bytes: MemcmpEncodedBytes::Base58(self.market.to_string()),
+            encoding: None,
+        })])?;
+
         let mut owner_pubs: Vec<Pubkey> = Vec::new();
 
-        for &debt in market_state.debts.iter() {
+        for (_, debt) in debts.iter() {
             if debt.qty > 0 {
                 owner_pubs.push(debt.borrower);
             }
@@ -116,14 +129,16 @@ impl Context {
         for opub in owner_pubs {
             let (upub, _bump) = Pubkey::find_program_address(&[&self.market.to_bytes(), &opub.to_bytes()], &self.program_id);
             let mut user_data = connection.get_account_data(&upub).unwrap();
-            let user_account = bytemuck::try_from_bytes_mut::<UserAccount>(&mut user_data[8..]).unwrap();
+            // Open orders and open debts are in the tail after UserAccount
+            let (user_head, user_tail) = user_data[8..].split_at_mut(std::mem::size_of::<UserAccount>());
+            let user_account = bytemuck::try_from_bytes_mut::<UserAccount>(user_head).unwrap();
 
-            let health = get_user_health_factor(&user_account, &market_state, &oracle_account_info);
+            let health = get_user_health_factor(&user_account, &market_state, price, now)?;
             println!("health {}", health);
 
             if health < 100 {
                 for i in 0..user_account.open_orders_cnt as usize {
-                    let order_id = user_account.open_orders[i];
+                    let order_id = user_account.get_open_order(user_tail, i);
 
                     if let Side::Ask = get_side_from_order_id(order_id) {
                         continue;
@@ -143,13 +158,44 @@ impl Context {
                     });
                     request = request.args(CancelRiskyOrderInst {
                         _bump,
-                        order_id: user_account.open_orders[i]
+                        order_id
                     });
                     request.send()?;
                     return Ok(Signature::new_unique());
                 }
 
-                let total_debt = get_user_total_debt(&user_account, &market_state);
+                // Collateral can't pay all debts, so the rest is written off for lenders
+                if is_user_insolvent(&user_account, &market_state, price, now)? {
+                    let mut borrower_debts: Vec<(&Pubkey, &Debt)> = Vec::new();
+                    for i in 0..user_account.open_debts_cnt as usize {
+                        let debt_id = user_account.get_open_debt(user_tail, i);
+                        if let Some((debt_pub, debt)) = debts.iter().find(|(_, d)| d.id == debt_id) {
+                            borrower_debts.push((debt_pub, debt));
+                        }
+                    }
+
+                    println!("Resolving bad debt of {}", opub);
+                    self.send_bad_debt_resolution(program, market_state, upub, borrower_debts)?;
+                    continue;
+                }
+
+                // Auction bonus starts from zero once it's opened, so open it and liquidate later
+                if market_state.liquidation_auction_duration > 0 && user_account.liquidation_auction_start == 0 {
+                    let mut request = program.request();
+                    request = request.accounts(UpdateLiquidationAuctionAccount {
+                        borrower_account: upub,
+                        market: self.market,
+                        price_oracle: market_state.price_oracle,
+                    });
+                    request = request.args(UpdateLiquidationAuctionInst {});
+                    request.send()?;
+                    println!("Opened liquidation auction of {}", opub);
+                    continue;
+                }
+
+                let total_debt = get_user_total_debt(&user_account, now)?;
+
+                let bonus_bps = market_state.get_liquidation_bonus_bps(user_account.liquidation_auction_start, now);
 
                 let mut lo = 0;
                 let mut hi = total_debt;
@@ -157,7 +203,7 @@ impl Context {
                 while lo + 1 < hi {
                     let mid = (lo + hi)/2;
 
-                    let health = get_user_health_factor_after_liquid(mid,&user_account, &market_state, &oracle_account_info);
+                    let health = get_user_health_factor_after_liquid(mid, bonus_bps, &user_account, &market_state, price, now)?;
 
                     if health < 100 {
                         lo = mid;
@@ -166,73 +212,167 @@ impl Context {
                     }
                 }
 
-                let mut liquid_value = hi;
+                // Program doesn't allow more than close factor of total debt at once
+                let mut liquid_value = hi.min(market_state.max_liquidation_qty(total_debt)?);
                 println!("Liquid value is {}", liquid_value);
-                let mut debt_ids: Vec<u16> = Vec::new();
-                let mut debt_qty: Vec<u64> = Vec::new();
+                let mut liquidations: Vec<(&Pubkey, &Debt, u64)> = Vec::new();
 
                 for i in 0..user_account.open_debts_cnt as usize {
-                    let debt_id = user_account.open_debts[i];
-                    debt_ids.push(debt_id);
-                    debt_qty.push(0);
+                    let debt_id = user_account.get_open_debt(user_tail, i);
+                    let (debt_pub, debt) = match debts.iter().find(|(_, d)| d.id == debt_id) {
+                        Some(d) => d,
+                        None => continue,
+                    };
+
+                    let take = market_state.max_liquidation_qty(debt.get_debt_as_of_now(now)?)?;
+                    let take = take.min(liquid_value);
+                    if take == 0 {
+                        continue;
+                    }
+                    liquidations.push((debt_pub, debt, take));
+                    liquid_value -= take;
                 }
 
-                let mut trial = 0;
-                while liquid_value > 0 {
-                    for i in 0..user_account.open_debts_cnt as usize {
-                        let debt_id = user_account.open_debts[i];
-                        let debt = &market_state.debts[debt_id as usize];
-
-                        let take = (debt.qty - debt.liquid_qty - debt_qty[i] + 1)/2;
-                        let take = take.min(liquid_value);
-                        debt_qty[i] += take;
-                        liquid_value -= take;
-
-                        if liquid_value == 0 {
-                            break;
-                        }
-                    }
-                    trial += 1;
-                    if trial > 10 {
-                        break;
-                    }
+                self.send_liquidation(program, market_state, upub, liquidations)?;
+            } else {
+                // Borrower recovered without any health check on chain, so close its auction before it goes stale
+                if user_account.liquidation_auction_start != 0 {
+                    let mut request = program.request();
+                    request = request.accounts(UpdateLiquidationAuctionAccount {
+                        borrower_account: upub,
+                        market: self.market,
+                        price_oracle: market_state.price_oracle,
+                    });
+                    request = request.args(UpdateLiquidationAuctionInst {});
+                    request.send()?;
+                    println!("Closed liquidation auction of {}", opub);
                 }
 
-                let market_signer = Pubkey::find_program_address(&[&self.market.to_bytes()], &balex::ID).0;
-
-                let mut request = program.request();
-                request = request.accounts(LiquidateDebtsAccount {
-                    liquidator: self.fee_payer.pubkey(),
-                    market: self.market,
-                    base_vault: market_state.base_vault,
-                    quote_vault: market_state.quote_vault,
-                    price_oracle: market_state.price_oracle,
-                    borrower_account: upub,
-                    token_program: spl_token::ID,
-                    market_signer,
-                    token_base_src: get_associated_token_address(&self.reward_target, &market_state.base_mint),
-                    token_quote_dest: get_associated_token_address(&self.reward_target, &market_state.quote_mint),
-                });
+                // Debts after maturity and grace period are liquidated completely even if borrower is healthy
+                let mut liquidations: Vec<(&Pubkey, &Debt, u64)> = Vec::new();
 
                 for i in 0..user_account.open_debts_cnt as usize {
-                    if debt_qty[i] == 0 { // Since i couldn't upgrade program
-                        continue;
+                    let debt_id = user_account.get_open_debt(user_tail, i);
+                    let (debt_pub, debt) = match debts.iter().find(|(_, d)| d.id == debt_id) {
+                        Some(d) => d,
+                        None => continue,
+                    };
+
+                    if debt.is_overdue(now, market_state.grace_period) {
+                        liquidations.push((debt_pub, debt, debt.get_debt_as_of_now(now)?));
                     }
-                    let debt_id = user_account.open_debts[i];
-                    let debt = &market_state.debts[debt_id as usize];
-                    let lender_user = Pubkey::find_program_address(&[&self.market.to_bytes(), &debt.lender.to_bytes()], &self.program_id).0;
-                    request = request.accounts(RemUserAccount{
-                        user_account: lender_user
-                    })
                 }
-                request = request.args(LiquidateDebtsInst {
-                    debts_amount: debt_qty,
-                    debts_id: debt_ids
-                });
-                request.send()?;
+
+                if !liquidations.is_empty() {
+                    println!("Liquidating {} overdue debts", liquidations.len());
+                    self.send_liquidation(program, market_state, upub, liquidations)?;
+                }
             }
         }
 
         Ok(Signature::new_unique())
     }
+
+    // Sends liquidation of given (debt account, debt, amount) of the borrower
+    fn send_liquidation(
+        &self,
+        program: &Program,
+        market_state: &LexMarket,
+        borrower_account: Pubkey,
+        liquidations: Vec<(&Pubkey, &Debt, u64)>,
+    ) -> Result<Signature, ClientError> {
+        let mut debt_ids: Vec<u64> = Vec::new();
+        let mut debt_qty: Vec<u64> = Vec::new();
+        let mut debt_pubs: Vec<Pubkey> = Vec::new();
+        let mut lender_pubs: Vec<Pubkey> = Vec::new();
+
+        for (debt_pub, debt, amount) in liquidations {
+            debt_ids.push(debt.id);
+            debt_qty.push(amount);
+            debt_pubs.push(*debt_pub);
+            lender_pubs.push(Pubkey::find_program_address(&[&self.market.to_bytes(), &debt.lender.to_bytes()], &self.program_id).0);
+        }
+
+        lender_pubs.sort_unstable();
+        lender_pubs.dedup();
+
+        let market_signer = Pubkey::find_program_address(&[&self.market.to_bytes()], &balex::ID).0;
+
+        let mut request = program.request();
+        request = request.accounts(LiquidateDebtsAccount {
+            liquidator: self.fee_payer.pubkey(),
+            market: self.market,
+            base_vault: market_state.base_vault,
+            quote_vault: market_state.quote_vault,
+            price_oracle: market_state.price_oracle,
+            borrower_account,
+            token_program: spl_token::ID,
+            market_signer,
+            token_base_src: get_associated_token_address(&self.reward_target, &market_state.base_mint),
+            token_quote_dest: get_associated_token_address(&self.reward_target, &market_state.quote_mint),
+        });
+
+        // Debt accounts go first in the same order of debt ids, then lender accounts
+        for debt_pub in debt_pubs {
+            request = request.accounts(RemUserAccount{
+                user_account: debt_pub
+            })
+        }
+        for lender_user in lender_pubs {
+            request = request.accounts(RemUserAccount{
+                user_account: lender_user
+            })
+        }
+        request = request.args(LiquidateDebtsInst {
+            debts_amount: debt_qty,
+            debts_id: debt_ids
+        });
+        request.send()
+    }
+
+    // Sends resolution of all (debt account, debt) of an insolvent borrower, in the order of its open debts
+    fn send_bad_debt_resolution(
+        &self,
+        program: &Program,
+        market_state: &LexMarket,
+        borrower_account: Pubkey,
+        borrower_debts: Vec<(&Pubkey, &Debt)>,
+    ) -> Result<Signature, ClientError> {
+        let mut lender_pubs: Vec<Pubkey> = borrower_debts
+            .iter()
+            .map(|(_, debt)| Pubkey::find_program_address(&[&self.market.to_bytes(), &debt.lender.to_bytes()], &self.program_id).0)
+            .collect();
+
+        lender_pubs.sort_unstable();
+        lender_pubs.dedup();
+
+        let market_signer = Pubkey::find_program_address(&[&self.market.to_bytes()], &balex::ID).0;
+
+        let mut request = program.request();
+        request = request.accounts(ResolveBadDebtAccount {
+            liquidator: self.fee_payer.pubkey(),
+            market: self.market,
+            base_vault: market_state.base_vault,
+            quote_vault: market_state.quote_vault,
+            price_oracle: market_state.price_oracle,
+            borrower_account,
+            token_program: spl_token::ID,
+            market_signer,
+            token_base_src: get_associated_token_address(&self.reward_target, &market_state.base_mint),
+            token_quote_dest: get_associated_token_address(&self.reward_target, &market_state.quote_mint),
+        });
+
+        for (debt_pub, _) in borrower_debts {
+            request = request.accounts(RemUserAccount{
+                user_account: *debt_pub
+            })
+        }
+        for lender_user in lender_pubs {
+            request = request.accounts(RemUserAccount{
+                user_account: lender_user
+            })
+        }
+        request = request.args(ResolveBadDebtInst {});
+        request.send()
+    }
 }
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+
+#[error]
+pub enum BalexError {
+    #[msg("Invalid order side, should be 0 (bid) or 1 (ask)")]
+    InvalidOrderSide,
+    #[msg("Vault address is not base nor quote vault of the market")]
+    InvalidVault,
+    #[msg("Insufficient free base balance")]
+    InsufficientBaseFree,
+    #[msg("Requested quantity is more than maximum allowed borrow")]
+    BorrowLimitExceeded,
+    #[msg("Requested quantity is more than maximum allowed withdrawal")]
+    WithdrawLimitExceeded,
+    #[msg("Max open orders reached")]
+    OpenOrdersLimitReached,
+    #[msg("Max open debts reached")]
+    OpenDebtsLimitReached,
+    #[msg("Order is not in user open orders")]
+    OrderNotFound,
+    #[msg("Debt is not in user open debts")]
+    DebtNotFound,
+    #[msg("Debt account is not provided")]
+    DebtAccountMissing,
+    #[msg("Debt account doesn't match the debt")]
+    InvalidDebtAccount,
+    #[msg("Debt is already settled")]
+    DebtSettled,
+    #[msg("Order summary is missing from event queue register")]
+    OrderSummaryMissing,
+    #[msg("Only bid orders can be cancelled as risky")]
+    RiskyOrderNotBid,
+    #[msg("User is in good shape with health factor >= 100")]
+    UserHealthy,
+    #[msg("Borrower has open borrow orders, cancel them first")]
+    BorrowerHasOpenBids,
+    #[msg("Debt id list size and debt amount list size are not equal")]
+    DebtListLengthMismatch,
+    #[msg("Borrower is not borrower of this debt")]
+    DebtBorrowerMismatch,
+    #[msg("Lender user account of debt is not provided")]
+    LenderAccountMissing,
+    #[msg("User account of event is not provided")]
+    UserAccountMissing,
+    #[msg("Liquidation amount is more than close factor allows")]
+    CloseFactorExceeded,
+    #[msg("Borrower doesn't have enough quote to pay for liquidation")]
+    InsufficientCollateral,
+    #[msg("Liquidation should bring health factor to >= 100")]
+    HealthStillTooLow,
+    #[msg("Price oracle is unavailable")]
+    OracleUnavailable,
+    #[msg("No event could be consumed")]
+    NoEventsConsumed,
+    #[msg("Orderbook instruction failed")]
+    OrderbookError,
+    #[msg("Invalid market params")]
+    InvalidMarketParams,
+    #[msg("Order size is less than market minimum")]
+    OrderSizeTooSmall,
+    #[msg("Interest rate is more than market maximum")]
+    InterestRateTooHigh,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Oracle price status is not trading")]
+    OraclePriceNotTrading,
+    #[msg("Oracle price is stale")]
+    OraclePriceStale,
+    #[msg("Oracle price confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Invalid open orders or open debts capacity of user account")]
+    InvalidUserAccountCapacity,
+    #[msg("Price oracle is not the price oracle of the market")]
+    InvalidPriceOracle,
+    #[msg("Token account mint or owner is not as expected")]
+    InvalidTokenAccount,
+    #[msg("User account doesn't belong to the market")]
+    InvalidUserAccount,
+    #[msg("Lender account doesn't belong to lender of the debt")]
+    InvalidLenderAccount,
+    #[msg("Repay amount should be more than zero")]
+    InvalidRepayAmount,
+    #[msg("User account still has balance, open orders or open debts")]
+    UserAccountNotEmpty,
+    #[msg("Market still has open debts or balance in vaults")]
+    MarketNotEmpty,
+    #[msg("Event queue of the market is not drained")]
+    EventQueueNotEmpty,
+    #[msg("Expiry timestamp should be zero or in the future")]
+    InvalidExpiryTimestamp,
+    #[msg("User has no expired order to purge")]
+    NoExpiredOrders,
+    #[msg("Post only order would match")]
+    PostOnlyWouldMatch,
+    #[msg("Fill or kill order is not filled completely")]
+    OrderNotFilled,
+    #[msg("Client order id is used by another open order")]
+    DuplicateClientOrderId,
+    #[msg("Borrower can pay its debts, liquidate it instead")]
+    BorrowerNotInsolvent,
+    #[msg("Market is reduce-only or paused")]
+    MarketNotActive,
+    #[msg("Market is paused")]
+    MarketPaused,
+    #[msg("Signer is not allowed to do this on the market")]
+    Unauthorized,
+    #[msg("Debt is not settled yet")]
+    DebtNotSettled,
+    #[msg("Account is not payer of the debt account")]
+    InvalidDebtPayer,
+    #[msg("Partial repay should be at least min order size")]
+    RepayAmountTooSmall,
+    #[msg("Market should be paused first")]
+    MarketNotPaused,
+    #[msg("User account has legacy layout, migrate it first")]
+    UserAccountNotMigrated,
+    #[msg("User account doesn't have legacy layout")]
+    UserAccountNotLegacy,
+    #[msg("Legacy open debts can't be migrated")]
+    LegacyDebtsOpen,
+}
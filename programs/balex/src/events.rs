@@ -0,0 +1,129 @@
+use anchor_lang::prelude::*;
+
+// Events are emitted with emit! and are part of the IDL, so clients don't need to parse logs.
+// Users are owners of the user accounts, side is 0 for bid (borrow) and 1 for ask (lend)
+
+#[event]
+pub struct OrderPlaced {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub order_id: u128, // Zero if nothing was posted to the orderbook
+    pub client_order_id: u64,
+    pub side: u8,
+    pub interest_rate: u64,
+    pub qty: u64, // Matched and posted qty
+    pub expiry_timestamp: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub order_id: u128,
+    pub side: u8,
+    pub qty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DebtCreated {
+    pub market: Pubkey,
+    pub debt_id: u64,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub qty: u64,
+    pub interest_rate: u64,
+    pub maturity: i64,
+    pub timestamp: i64,
+}
+
+// Emitted for partial repayments too, debt is settled when remaining_qty is zero
+#[event]
+pub struct DebtSettled {
+    pub market: Pubkey,
+    pub debt_id: u64,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub amount: u64,
+    pub protocol_fee: u64,
+    pub remaining_qty: u64,
+    pub interest_rate: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DebtLiquidated {
+    pub market: Pubkey,
+    pub debt_id: u64,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub liquidator: Pubkey,
+    pub amount: u64,
+    pub protocol_fee: u64,
+    pub interest_rate: u64,
+    pub overdue: bool,
+    pub bonus_bps: u64, // Liquidation bonus paid in quote, overdue debts always get the full bonus
+    pub timestamp: i64,
+}
+
+// Emitted for each debt closed by resolve_bad_debt, loss is what the lender doesn't get back
+#[event]
+pub struct DebtWrittenOff {
+    pub market: Pubkey,
+    pub debt_id: u64,
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub recovered: u64,
+    pub loss: u64,
+    pub interest_rate: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BadDebtResolved {
+    pub market: Pubkey,
+    pub borrower: Pubkey,
+    pub liquidator: Pubkey,
+    pub total_debt: u64,
+    pub seized_quote: u64, // All collateral of the borrower, given to the liquidator
+    pub recovered: u64, // Base paid by the liquidator, base free of the borrower and insurance
+    pub insurance_used: u64,
+    pub shortfall: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceDeposit {
+    pub market: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub insurance_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceRetired {
+    pub market: Pubkey,
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Deposit {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Withdraw {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
@@ -3,6 +3,8 @@ use anchor_lang::{AnchorDeserialize, AnchorSerialize};
 
 declare_id!("HSqPL6LuWSVpztWHWD1yZh36tugGdUC6s5SLSTddTnDw");
 
+pub mod error;
+pub mod events;
 pub mod state;
 pub mod processor;
 
@@ -16,11 +18,30 @@ pub mod balex {
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         signer_bump: u8,
-        base_mint: Pubkey,
-        quote_mint: Pubkey,
         oracle_type: OracleType,
+        loan_term: u64,
     ) -> ProgramResult {
-        processor::market::initialize_market(ctx, signer_bump, base_mint, quote_mint, oracle_type)
+        processor::market::initialize_market(ctx, signer_bump, oracle_type, loan_term)
+    }
+
+    pub fn update_market_params(ctx: Context<UpdateMarketParams>, params: MarketParams) -> ProgramResult {
+        processor::market::update_market_params(ctx, params)
+    }
+
+    pub fn set_market_status(ctx: Context<SetMarketStatus>, status: MarketStatus) -> ProgramResult {
+        processor::market::set_market_status(ctx, status)
+    }
+
+    pub fn set_roles(ctx: Context<SetRoles>, risk_admin: Pubkey, fee_admin: Pubkey, guardian: Pubkey) -> ProgramResult {
+        processor::market::set_roles(ctx, risk_admin, fee_admin, guardian)
+    }
+
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> ProgramResult {
+        processor::market::propose_admin(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> ProgramResult {
+        processor::market::accept_admin(ctx)
     }
 
     pub fn initialize_account(
@@ -30,6 +51,19 @@ pub mod balex {
         processor::account::initialize_account(ctx, _bump)
     }
 
+    pub fn resize_user_account(
+        ctx: Context<ResizeUserAccount>,
+        _bump: u8,
+        open_orders_capacity: u16,
+        open_debts_capacity: u16,
+    ) -> ProgramResult {
+        processor::account::resize_user_account(ctx, _bump, open_orders_capacity, open_debts_capacity)
+    }
+
+    pub fn migrate_user_account(ctx: Context<MigrateUserAccount>, _bump: u8) -> ProgramResult {
+        processor::account::migrate_user_account(ctx, _bump)
+    }
+
     pub fn deposit(ctx: Context<Deposit>, _bump: u8, amount: u64) -> ProgramResult {
         processor::account::deposit(ctx, _bump, amount)
     }
@@ -38,18 +72,54 @@ pub mod balex {
         processor::stub_oracle::set_stub_price(ctx, price, conf)
     }
 
-    pub fn new_order(ctx: Context<NewOrder>, _bump: u8, side_num: u8, interest_rate: u64, qty: u64) -> ProgramResult {
-        processor::order::new_order(ctx, _bump, side_num, interest_rate, qty)
+    pub fn new_order(
+        ctx: Context<NewOrder>,
+        _bump: u8,
+        side_num: u8,
+        interest_rate: u64,
+        qty: u64,
+        expiry_timestamp: i64,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeBehavior,
+        client_order_id: u64,
+    ) -> ProgramResult {
+        processor::order::new_order(ctx, _bump, side_num, interest_rate, qty, expiry_timestamp, order_type, self_trade_behavior, client_order_id)
     }
 
     pub fn cancel_my_order(ctx: Context<CancelMyOrder>, _bump: u8, order_id: u128) -> ProgramResult {
         processor::order::cancel_my_order(ctx, _bump, order_id)
     }
 
+    pub fn cancel_by_client_order_id(ctx: Context<CancelMyOrder>, _bump: u8, client_order_id: u64) -> ProgramResult {
+        processor::order::cancel_by_client_order_id(ctx, _bump, client_order_id)
+    }
+
+    pub fn cancel_all_orders(ctx: Context<CancelMyOrder>, _bump: u8, side_num: Option<u8>) -> ProgramResult {
+        processor::order::cancel_all_orders(ctx, _bump, side_num)
+    }
+
+    pub fn replace_order(
+        ctx: Context<NewOrder>,
+        _bump: u8,
+        order_id: u128,
+        interest_rate: u64,
+        qty: u64,
+        expiry_timestamp: i64,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeBehavior,
+        client_order_id: u64,
+    ) -> ProgramResult {
+        processor::order::replace_order(ctx, _bump, order_id, interest_rate, qty, expiry_timestamp, order_type, self_trade_behavior, client_order_id)
+    }
+
     pub fn cancel_risky_order(ctx: Context<CancelRiskyOrder>, _bump: u8, order_id: u128) -> ProgramResult {
         processor::order::cancel_risky_order(ctx, _bump, order_id)
     }
 
+    pub fn purge_expired_orders(ctx: Context<PurgeExpiredOrders>, max_orders: u16) -> ProgramResult {
+        processor::order::purge_expired_orders(ctx, max_orders)
+    }
+
     pub fn consume_order_events(
         ctx: Context<ConsumerOrderEvents>,
         max_iterations: u64,
@@ -62,18 +132,51 @@ pub mod balex {
         processor::account::withdraw(ctx, _bump, amount)
     }
 
-    pub fn settle_debt(ctx: Context<SettleDebt>, _bump: u8, debt_id: u16) -> ProgramResult {
-        processor::debt::settle_debt(ctx, _bump, debt_id)
+    pub fn settle_debt(ctx: Context<SettleDebt>, _bump: u8, debt_id: u64, amount: u64) -> ProgramResult {
+        processor::debt::settle_debt(ctx, _bump, debt_id, amount)
     }
 
-    pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u16>, debts_amount: Vec<u64>) -> ProgramResult {
+    pub fn repay_from_wallet(ctx: Context<RepayFromWallet>, debt_id: u64, amount: u64) -> ProgramResult {
+        processor::debt::repay_from_wallet(ctx, debt_id, amount)
+    }
+
+    pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u64>, debts_amount: Vec<u64>) -> ProgramResult {
         processor::debt::liquidate_debts(ctx, debts_id, debts_amount)
     }
 
+    pub fn close_debt(ctx: Context<CloseDebt>) -> ProgramResult {
+        processor::debt::close_debt(ctx)
+    }
+
+    pub fn update_liquidation_auction(ctx: Context<UpdateLiquidationAuction>) -> ProgramResult {
+        processor::debt::update_liquidation_auction(ctx)
+    }
+
+    pub fn resolve_bad_debt(ctx: Context<ResolveBadDebt>) -> ProgramResult {
+        processor::debt::resolve_bad_debt(ctx)
+    }
+
     pub fn order_dummy(ctx: Context<RemUserAccount>) -> ProgramResult {
         processor::order::order_dummy(ctx)
     }
 
-    // close account?
-    // close market?
+    pub fn claim_fees(ctx: Context<ClaimFees>) -> ProgramResult {
+        processor::market::claim_fees(ctx)
+    }
+
+    pub fn deposit_insurance(ctx: Context<DepositInsurance>, amount: u64) -> ProgramResult {
+        processor::market::deposit_insurance(ctx, amount)
+    }
+
+    pub fn retire_insurance(ctx: Context<RetireInsurance>) -> ProgramResult {
+        processor::market::retire_insurance(ctx)
+    }
+
+    pub fn close_user_account(ctx: Context<CloseUserAccount>, _bump: u8) -> ProgramResult {
+        processor::account::close_user_account(ctx, _bump)
+    }
+
+    pub fn close_market(ctx: Context<CloseMarket>) -> ProgramResult {
+        processor::market::close_market(ctx)
+    }
 }
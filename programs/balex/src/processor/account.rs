@@ -1,6 +1,13 @@
 use crate::get_max_withdraw_qty;
+use crate::get_quote_price;
+use crate::error::BalexError;
+use crate::events;
 use anchor_lang::prelude::*;
-use crate::state::{UserAccount, LexMarket};
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::system_instruction;
+use crate::state::{get_user_account_space, is_legacy_user_account, migrate_legacy_user_account, UserAccount, LexMarket};
+use crate::state::{OPEN_DEBT_LEN, OPEN_ORDER_LEN, USER_MAX_OPEN_DEBTS_SIZE, USER_MAX_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE, USER_OPEN_ORDERS_SIZE};
 use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
 
 #[derive(Accounts)]
@@ -9,10 +16,10 @@ pub struct InitializeAccount<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
 
-    #[account()]
+    #[account(constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
     pub market: AccountLoader<'info, LexMarket>,
 
-    #[account(init, payer=owner, space = 8 + std::mem::size_of::<UserAccount>(), seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
+    #[account(init, payer=owner, space = get_user_account_space(USER_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE), seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
     pub user_account: AccountLoader<'info, UserAccount>,
 
     #[account()]
@@ -29,13 +36,13 @@ pub struct Deposit<'info> {
     // TODO: If it's gonna be per market also add market here
     pub user_account: AccountLoader<'info, UserAccount>,
 
-    #[account()]
+    #[account(constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
     pub market: AccountLoader<'info, LexMarket>,
 
     #[account(mut)]
     pub vault: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = token_source.mint == vault.mint @ BalexError::InvalidTokenAccount)]
     pub token_source: Account<'info, TokenAccount>,
 
     #[account()]
@@ -49,6 +56,143 @@ pub fn initialize_account(
     let mut user_account = ctx.accounts.user_account.load_init()?;
 
     user_account.owner = ctx.accounts.owner.key();
+    user_account.open_orders_capacity = USER_OPEN_ORDERS_SIZE as u16;
+    user_account.open_debts_capacity = USER_OPEN_DEBTS_SIZE as u16;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(_bump: u8)]
+pub struct ResizeUserAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+
+    #[account(constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account()]
+    pub system_program: Program<'info, System>,
+}
+
+// Changes capacity of open orders and open debts, owner pays for the extra rent or gets back the freed one
+pub fn resize_user_account(
+    ctx: Context<ResizeUserAccount>,
+    _bump: u8,
+    open_orders_capacity: u16,
+    open_debts_capacity: u16,
+) -> ProgramResult {
+    let user_account_info = ctx.accounts.user_account.to_account_info();
+    let owner_info = ctx.accounts.owner.to_account_info();
+
+    let (old_debts_offset, open_debts_cnt) = {
+        let user_account = ctx.accounts.user_account.load()?;
+
+        if open_orders_capacity < user_account.open_orders_cnt || open_orders_capacity as usize > USER_MAX_OPEN_ORDERS_SIZE {
+            msg!("Open orders capacity should be between {} and {}", user_account.open_orders_cnt, USER_MAX_OPEN_ORDERS_SIZE);
+            return Err(BalexError::InvalidUserAccountCapacity.into());
+        }
+
+        if open_debts_capacity < user_account.open_debts_cnt || open_debts_capacity as usize > USER_MAX_OPEN_DEBTS_SIZE {
+            msg!("Open debts capacity should be between {} and {}", user_account.open_debts_cnt, USER_MAX_OPEN_DEBTS_SIZE);
+            return Err(BalexError::InvalidUserAccountCapacity.into());
+        }
+
+        (user_account.open_debts_offset(), user_account.open_debts_cnt as usize)
+    };
+
+    let old_len = user_account_info.data_len();
+    let new_len = get_user_account_space(open_orders_capacity as usize, open_debts_capacity as usize);
+
+    if new_len > old_len + MAX_PERMITTED_DATA_INCREASE {
+        msg!("Account can grow at most {} bytes at once, resize it in several steps", MAX_PERMITTED_DATA_INCREASE);
+        return Err(BalexError::InvalidUserAccountCapacity.into());
+    }
+
+    let tail_offset = 8 + std::mem::size_of::<UserAccount>();
+    let new_debts_offset = open_orders_capacity as usize * OPEN_ORDER_LEN;
+    let debts_len = open_debts_cnt * OPEN_DEBT_LEN;
+
+    let move_debts = || -> ProgramResult {
+        let mut data = user_account_info.try_borrow_mut_data()?;
+        let tail = &mut data[tail_offset..];
+        tail.copy_within(old_debts_offset..old_debts_offset + debts_len, new_debts_offset);
+        Ok(())
+    };
+
+    let new_minimum_balance = Rent::get()?.minimum_balance(new_len);
+
+    if new_len >= old_len {
+        let required_lamports = new_minimum_balance.saturating_sub(user_account_info.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(owner_info.key, user_account_info.key, required_lamports),
+                &[owner_info.clone(), user_account_info.clone(), ctx.accounts.system_program.to_account_info()],
+            )?;
+        }
+        user_account_info.realloc(new_len, true)?;
+        move_debts()?;
+    } else {
+        move_debts()?;
+        user_account_info.realloc(new_len, false)?;
+
+        let extra_lamports = user_account_info.lamports().saturating_sub(new_minimum_balance);
+        **user_account_info.try_borrow_mut_lamports()? -= extra_lamports;
+        **owner_info.try_borrow_mut_lamports()? += extra_lamports;
+    }
+
+    let mut user_account = ctx.accounts.user_account.load_mut()?;
+    user_account.open_orders_capacity = open_orders_capacity;
+    user_account.open_debts_capacity = open_debts_capacity;
+
+    msg!("User account resized to {} open orders and {} open debts", open_orders_capacity, open_debts_capacity);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(_bump: u8)]
+pub struct MigrateUserAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+
+    #[account(constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account()]
+    pub system_program: Program<'info, System>,
+}
+
+// Moves open orders of a user account created with fixed arrays into the tail with default capacities,
+// owner pays for the extra rent
+pub fn migrate_user_account(ctx: Context<MigrateUserAccount>, _bump: u8) -> ProgramResult {
+    let user_account_info = ctx.accounts.user_account.to_account_info();
+    let owner_info = ctx.accounts.owner.to_account_info();
+
+    if !is_legacy_user_account(&user_account_info.try_borrow_data()?) {
+        msg!("User account is already migrated");
+        return Err(BalexError::UserAccountNotLegacy.into());
+    }
+
+    let new_len = get_user_account_space(USER_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE);
+    let required_lamports = Rent::get()?.minimum_balance(new_len).saturating_sub(user_account_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(owner_info.key, user_account_info.key, required_lamports),
+            &[owner_info.clone(), user_account_info.clone(), ctx.accounts.system_program.to_account_info()],
+        )?;
+    }
+    user_account_info.realloc(new_len, false)?;
+
+    migrate_legacy_user_account(&mut user_account_info.try_borrow_mut_data()?)?;
+
+    msg!("User account of {} is migrated", ctx.accounts.owner.key());
 
     Ok(())
 }
@@ -63,7 +207,7 @@ pub fn deposit(ctx: Context<Deposit>, _bump: u8, amount: u64) -> ProgramResult {
         user_account.quote_total += amount;
     } else {
         msg!("Vault address is not base nor quote vault of the market");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(BalexError::InvalidVault.into());
     }
 
     transfer(
@@ -78,6 +222,14 @@ pub fn deposit(ctx: Context<Deposit>, _bump: u8, amount: u64) -> ProgramResult {
         amount,
     )?;
 
+    emit!(events::Deposit {
+        market: ctx.accounts.market.key(),
+        user: ctx.accounts.owner.key(),
+        mint: ctx.accounts.vault.mint,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -92,16 +244,16 @@ pub struct Withdraw<'info> {
     // TODO: If it's gonna be per market also add market here
     pub user_account: AccountLoader<'info, UserAccount>,
 
-    #[account()]
+    #[account(has_one = price_oracle @ BalexError::InvalidPriceOracle, constraint = market.load()?.is_active() @ BalexError::MarketNotActive)]
     pub market: AccountLoader<'info, LexMarket>,
 
-    #[account()]
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
     pub market_signer: AccountInfo<'info>,
 
     #[account(mut)]
     pub vault: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = token_dest.mint == vault.mint @ BalexError::InvalidTokenAccount)]
     pub token_dest: Account<'info, TokenAccount>,
 
     #[account()]
@@ -119,22 +271,24 @@ pub fn withdraw(ctx: Context<Withdraw>, _bump: u8, amount: u64) -> ProgramResult
     if ctx.accounts.vault.key() == market.base_vault {
         if amount > user_account.base_free {
             msg!("You don't have sufficient funds in your account to withdraw");
-            return Err(ProgramError::InsufficientFunds);
+            return Err(BalexError::InsufficientBaseFree.into());
         }
         user_account.base_free -= amount;
     } else if ctx.accounts.vault.key() == market.quote_vault {
-        let max_withdraw = get_max_withdraw_qty(&user_account, &market, &ctx.accounts.price_oracle);
+        let clock = Clock::get()?;
+        let price = get_quote_price(&market, &ctx.accounts.price_oracle, clock.slot)?;
+        let max_withdraw = get_max_withdraw_qty(&user_account, &market, price, clock.unix_timestamp)?;
 
         if amount > max_withdraw {
             msg!("You don't have sufficient funds in your account to withdraw");
             msg!("Maximum withdrawal is {}", max_withdraw);
-            return Err(ProgramError::InsufficientFunds);
+            return Err(BalexError::WithdrawLimitExceeded.into());
         }
 
         user_account.quote_total -= amount;
     } else {
         msg!("Vault address is not base nor quote vault of the market");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(BalexError::InvalidVault.into());
     }
 
     transfer(
@@ -150,5 +304,45 @@ pub fn withdraw(ctx: Context<Withdraw>, _bump: u8, amount: u64) -> ProgramResult
         amount,
     )?;
 
+    emit!(events::Withdraw {
+        market: ctx.accounts.market.key(),
+        user: ctx.accounts.owner.key(),
+        mint: ctx.accounts.vault.mint,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+#[instruction(_bump: u8)]
+pub struct CloseUserAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump, close=owner)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+
+    #[account(constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+pub fn close_user_account(ctx: Context<CloseUserAccount>, _bump: u8) -> ProgramResult {
+    let user_account = ctx.accounts.user_account.load()?;
+
+    if user_account.base_free > 0
+        || user_account.base_locked > 0
+        || user_account.base_open_lend > 0
+        || user_account.base_open_borrow > 0
+        || user_account.quote_total > 0
+        || user_account.open_orders_cnt > 0
+        || user_account.open_debts_cnt > 0
+    {
+        msg!("Withdraw all balances and close all orders and debts first");
+        return Err(BalexError::UserAccountNotEmpty.into());
+    }
+
     Ok(())
 }
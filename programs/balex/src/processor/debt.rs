@@ -1,13 +1,73 @@
 use crate::Debt;
 use crate::get_quote_price;
 use crate::get_user_health_factor;
+use crate::get_user_total_debt;
+use crate::error::BalexError;
+use crate::events::{BadDebtResolved, DebtLiquidated, DebtWrittenOff};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::system_instruction;
 use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
-use crate::state::{UserAccount, LexMarket};
+use crate::state::{
+    get_collateral_seizure, get_debt_address, get_user_account_address, is_user_insolvent, load_user_account_mut,
+    pay_debt, repay_debt, refresh_liquidation_auction, UserAccount, LexMarket,
+};
+
+// Creates PDA of a new debt paid by payer, it will be initialized by create_debt
+pub fn create_debt_account<'info>(
+    program_id: &Pubkey,
+    market_key: &Pubkey,
+    debt_id: u64,
+    debt_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<AccountLoader<'info, Debt>, ProgramError> {
+    let (debt_key, bump) = get_debt_address(market_key, debt_id, program_id);
+    if debt_key != debt_account.key() {
+        msg!("Debt account of debt {} should be {}", debt_id, debt_key);
+        return Err(BalexError::InvalidDebtAccount.into());
+    }
+
+    let space = 8 + std::mem::size_of::<Debt>();
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    let market_bytes = market_key.to_bytes();
+    let debt_id_bytes = debt_id.to_le_bytes();
+    let signer_seeds: &[&[u8]] = &[&market_bytes, &debt_id_bytes, &[bump]];
+
+    if debt_account.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(payer.key, debt_account.key, lamports, space as u64, program_id),
+            &[payer.clone(), debt_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    } else {
+        // Someone has sent lamports to the address beforehand, so it can't be created directly
+        let required_lamports = lamports.saturating_sub(debt_account.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, debt_account.key, required_lamports),
+                &[payer.clone(), debt_account.clone(), system_program.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(debt_account.key, space as u64),
+            &[debt_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(debt_account.key, program_id),
+            &[debt_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    }
+
+    AccountLoader::try_from_unchecked(program_id, debt_account)
+}
 
 
 #[derive(Accounts)]
-#[instruction(_bump: u8)]
+#[instruction(_bump: u8, debt_id: u64)]
 pub struct SettleDebt<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
@@ -15,34 +75,105 @@ pub struct SettleDebt<'info> {
     #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
     pub borrower_account: AccountLoader<'info, UserAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds=[&market.key().to_bytes(), &debt.load()?.lender.to_bytes()], bump,
+        constraint = lender_account.load()?.owner == debt.load()?.lender @ BalexError::InvalidLenderAccount,
+    )]
     pub lender_account: AccountLoader<'info, UserAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
     pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(
+        mut,
+        seeds=[&market.key().to_bytes(), &debt_id.to_le_bytes()], bump,
+        constraint = debt.load()?.borrower == owner.key() @ BalexError::DebtBorrowerMismatch,
+    )]
+    pub debt: AccountLoader<'info, Debt>,
 }
 
-pub fn settle_debt(ctx: Context<SettleDebt>, _bump: u8, debt_id: u16) -> ProgramResult {
-    let borrower_account = &mut ctx.accounts.borrower_account.load_mut()?;
-    let lender_account = &mut ctx.accounts.lender_account.load_mut()?;
+// Repays `amount` of the debt from base_free of borrower, amount more than the debt (e.g. u64::MAX) settles it fully
+pub fn settle_debt(ctx: Context<SettleDebt>, _bump: u8, _debt_id: u64, amount: u64) -> ProgramResult {
+    let borrower_account = &mut load_user_account_mut(&ctx.accounts.borrower_account)?;
+    let lender_account = &mut load_user_account_mut(&ctx.accounts.lender_account)?;
     let market = &mut ctx.accounts.market.load_mut()?;
+    let debt = &mut ctx.accounts.debt.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
 
-    let debt = &mut market.debts[debt_id as usize];
-    let debt_qty_now = debt.get_debt_as_of_now();
+    let debt_qty_now = debt.get_debt_as_of_now(now)?;
+    let amount = amount.min(debt_qty_now);
 
-    if borrower_account.base_free < debt_qty_now {
-        msg!("Insufficiant base qty {}, required {}", borrower_account.base_free, debt_qty_now);
-        return Err(ProgramError::InsufficientFunds);
+    if borrower_account.base_free < amount {
+        msg!("Insufficiant base qty {}, required {}", borrower_account.base_free, amount);
+        return Err(BalexError::InsufficientBaseFree.into());
     }
 
-    borrower_account.base_free -= debt_qty_now;
-    lender_account.base_locked -= debt.qty.saturating_sub(debt.liquid_qty);
-    lender_account.base_free += debt_qty_now;
+    let repaid = repay_debt(debt, amount, lender_account, borrower_account, market, now)?;
+    borrower_account.base_free -= repaid;
 
-    debt.qty = 0;
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+#[instruction(debt_id: u64)]
+pub struct RepayFromWallet<'info> {
+    // Anyone can repay the debt on behalf of the borrower
+    #[account()]
+    pub payer: Signer<'info>,
+
+    #[account(mut, constraint = token_base_src.mint == base_vault.mint @ BalexError::InvalidTokenAccount)]
+    pub token_base_src: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &debt.load()?.borrower.to_bytes()], bump)]
+    pub borrower_account: AccountLoader<'info, UserAccount>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &debt.load()?.lender.to_bytes()], bump)]
+    pub lender_account: AccountLoader<'info, UserAccount>,
+
+    #[account(mut, has_one = base_vault @ BalexError::InvalidVault, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
 
-    borrower_account.remove_debt(debt_id)?;
-    lender_account.remove_debt(debt_id)?;
+    #[account(mut, seeds=[&market.key().to_bytes(), &debt_id.to_le_bytes()], bump)]
+    pub debt: AccountLoader<'info, Debt>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+// Same as settle_debt but base is paid from token account of payer instead of borrower base_free
+// As anyone can call it, a partial repay should be at least min_order_size so it can't be spammed on a debt
+pub fn repay_from_wallet(ctx: Context<RepayFromWallet>, _debt_id: u64, amount: u64) -> ProgramResult {
+    let repaid = {
+        let borrower_account = &mut load_user_account_mut(&ctx.accounts.borrower_account)?;
+        let lender_account = &mut load_user_account_mut(&ctx.accounts.lender_account)?;
+        let market = &mut ctx.accounts.market.load_mut()?;
+        let debt = &mut ctx.accounts.debt.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        if amount < market.min_order_size && amount < debt.get_debt_as_of_now(now)? {
+            msg!("Repay amount {} is less than min order size {}", amount, market.min_order_size);
+            return Err(BalexError::RepayAmountTooSmall.into());
+        }
+
+        repay_debt(debt, amount, lender_account, borrower_account, market, now)?
+    };
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.token_base_src.to_account_info(),
+                to: ctx.accounts.base_vault.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            }
+        ),
+        repaid,
+    )?;
 
     Ok(())
 }
@@ -54,13 +185,19 @@ pub struct LiquidateDebts<'info> {
     #[account()]
     pub liquidator: Signer<'info>,
 
-    #[account(mut)]
+    #[account(mut, seeds=[&market.key().to_bytes(), &borrower_account.load()?.owner.to_bytes()], bump)]
     pub borrower_account: AccountLoader<'info, UserAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = price_oracle @ BalexError::InvalidPriceOracle,
+        has_one = base_vault @ BalexError::InvalidVault,
+        has_one = quote_vault @ BalexError::InvalidVault,
+        constraint = !market.load()?.is_paused() @ BalexError::MarketPaused,
+    )]
     pub market: AccountLoader<'info, LexMarket>,
 
-    #[account()]
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
     pub market_signer: AccountInfo<'info>,
 
     #[account(mut)]
@@ -69,10 +206,10 @@ pub struct LiquidateDebts<'info> {
     #[account(mut)]
     pub quote_vault: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = token_base_src.mint == base_vault.mint @ BalexError::InvalidTokenAccount)]
     pub token_base_src: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = token_quote_dest.mint == quote_vault.mint @ BalexError::InvalidTokenAccount)]
     pub token_quote_dest: Account<'info, TokenAccount>,
 
     #[account()]
@@ -83,38 +220,39 @@ pub struct LiquidateDebts<'info> {
 }
 
 
-pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u16>, debts_amount: Vec<u64>) -> ProgramResult {
-    let borrower_account = &mut ctx.accounts.borrower_account.load_mut()?;
+// Debt accounts should be given in remaining_accounts in the same order of debts_id, followed by lender user accounts
+pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u64>, debts_amount: Vec<u64>) -> ProgramResult {
+    let borrower_account = &mut load_user_account_mut(&ctx.accounts.borrower_account)?;
     let market = &mut ctx.accounts.market.load_mut()?;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let price = get_quote_price(&market, &ctx.accounts.price_oracle, clock.slot)?;
 
-    let borrower_health = get_user_health_factor(&borrower_account, &market, &ctx.accounts.price_oracle);
+    let borrower_health = get_user_health_factor(&borrower_account, &market, price, now)?;
 
-    if borrower_health >= 100 {
-        msg!("Borrower is healthy! Health: {}", borrower_health);
-        return Err(ProgramError::InvalidAccountData);
-    }
+    // Auction opens as soon as borrower is seen unhealthy, so its bonus starts from zero
+    refresh_liquidation_auction(borrower_account, borrower_health, now);
+    let auction_bonus_bps = market.get_liquidation_bonus_bps(borrower_account.liquidation_auction_start, now);
+    let max_bonus_bps = market.liquidation_bonus_percent as u64 * 100;
 
-    if borrower_account.base_open_borrow > 0 {
-        msg!("Borrower has some borrow request order, close it first!");
-        return Err(ProgramError::Custom(0));
+    if debts_id.len() != debts_amount.len() {
+        msg!("Debt id list size and Debt amount list size are not equal!");
+        return Err(BalexError::DebtListLengthMismatch.into());
     }
 
-    let mut lender_accounts: Vec<(&AccountInfo, Pubkey)> = Vec::new(); 
-
-    for acc in ctx.remaining_accounts.iter() {
-        let acc_loader: AccountLoader<UserAccount> = AccountLoader::try_from(acc)?;
-        let acc_data = acc_loader.load()?;
-        lender_accounts.push((acc, acc_data.owner))
+    if ctx.remaining_accounts.len() < debts_id.len() {
+        msg!("Debt accounts should be given before lender accounts");
+        return Err(BalexError::DebtAccountMissing.into());
     }
 
-    lender_accounts.sort_unstable_by_key(|a| a.1);
-
-    if debts_id.len() != debts_amount.len() {
-        msg!("Debt id list size and Debt amount list size are not equal!");
-        return Err(ProgramError::InvalidArgument);
-    }
+    let (debt_accounts, lender_account_infos) = ctx.remaining_accounts.split_at(debts_id.len());
+    let lender_accounts = get_lender_accounts(&ctx.accounts.market.key(), ctx.program_id, lender_account_infos)?;
 
+    // Close factor is applied both per debt and on total debt of the borrower
+    // Overdue debts can be liquidated completely and regardless of health, so only the others count toward it
+    let max_total_liquid = market.max_liquidation_qty(get_user_total_debt(&borrower_account, now)?)?;
     let mut total_base: u64 = 0;
+    let mut risky_base: u64 = 0;
 
     for i in 0..debts_id.len() {
         let debt_id = debts_id[i];
@@ -124,46 +262,126 @@ pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u16>, debts_a
             continue;
         }
 
-        let debt: &mut Debt = &mut market.debts[debt_id as usize];
+        let debt_loader: AccountLoader<Debt> = AccountLoader::try_from(&debt_accounts[i])?;
+        let debt = &mut debt_loader.load_mut()?;
+        if debt.market != ctx.accounts.market.key() || debt.id != debt_id {
+            msg!("Debt account {} is not debt {}", debt_accounts[i].key(), debt_id);
+            return Err(BalexError::InvalidDebtAccount.into());
+        }
+
+        if debt.qty == 0 {
+            msg!("Debt {} is already settled", debt_id);
+            return Err(BalexError::DebtSettled.into());
+        }
+
+        let debt_qty_now = debt.get_debt_as_of_now(now)?;
+        let close_factor_percent = market.close_factor_percent;
+
         if debt.borrower != borrower_account.owner {
             msg!("Borrower is not borrower of this debt {}", debt_id);
-            return Err(ProgramError::InvalidAccountData)
+            return Err(BalexError::DebtBorrowerMismatch.into())
         }
 
-        let debt_qty_now = debt.get_debt_as_of_now();
-        let max_allowed_liquid = (debt_qty_now + 1)/2;
-        if amount > max_allowed_liquid {
-            msg!("Max allowed liquid is ceil(50%) {}, skipping for now", max_allowed_liquid);
-            // return Err(ProgramError::Custom(0));
-        }
+        let overdue = debt.is_overdue(now, market.grace_period);
+        if overdue {
+            if amount > debt_qty_now {
+                msg!("Debt {} is overdue, remaining repay room is {}", debt_id, debt_qty_now);
+                return Err(BalexError::CloseFactorExceeded.into());
+            }
+        } else {
+            if borrower_health >= 100 {
+                msg!("Borrower is healthy and debt {} is not overdue! Health: {}", debt_id, borrower_health);
+                return Err(BalexError::UserHealthy.into());
+            }
 
+            let max_allowed_liquid = market.max_liquidation_qty(debt_qty_now)?;
+            if amount > max_allowed_liquid {
+                msg!("Max allowed liquid of debt {} is ceil({}%), remaining repay room is {}", debt_id, close_factor_percent, max_allowed_liquid);
+                return Err(BalexError::CloseFactorExceeded.into());
+            }
 
-        let lender_id = lender_accounts.binary_search_by_key(&debt.lender, |a| a.1).unwrap();
-        let lender_account_loader: AccountLoader<UserAccount> = AccountLoader::try_from(lender_accounts[lender_id].0)?;
-        let lender = &mut lender_account_loader.load_mut()?;
+            if risky_base + amount > max_total_liquid {
+                msg!("Max allowed liquid of borrower is ceil({}%), remaining repay room is {}", close_factor_percent, max_total_liquid - risky_base);
+                return Err(BalexError::CloseFactorExceeded.into());
+            }
+        }
 
-        lender.base_locked -= amount.min(debt.qty.saturating_sub(debt.liquid_qty));
-        lender.base_free += amount;
+        let lender_id = lender_accounts.binary_search_by_key(&debt.lender, |a| a.1).map_err(|_| {
+            msg!("Lender account of debt {} is not provided", debt_id);
+            BalexError::LenderAccountMissing
+        })?;
+        let lender_account_loader: AccountLoader<UserAccount> = AccountLoader::try_from(lender_accounts[lender_id].0)?;
+        let lender = &mut load_user_account_mut(&lender_account_loader)?;
+
+        // Liquidation is a repayment by liquidator, debt is rebased to now and closed once nothing remains
+        let (amount, fee) = pay_debt(debt, amount, lender, borrower_account, market, now)?;
+        if debt.qty == 0 {
+            market.open_debts_cnt -= 1;
+            borrower_account.remove_debt(debt_id)?;
+            lender.remove_debt(debt_id)?;
+        }
 
-        debt.liquid_qty += amount;
+        if !overdue {
+            risky_base += amount;
+        }
         total_base += amount;
+
+        emit!(DebtLiquidated {
+            market: debt.market,
+            debt_id,
+            lender: debt.lender,
+            borrower: debt.borrower,
+            liquidator: ctx.accounts.liquidator.key(),
+            amount,
+            protocol_fee: fee,
+            interest_rate: debt.interest_rate,
+            overdue,
+            bonus_bps: if overdue { max_bonus_bps } else { auction_bonus_bps },
+            timestamp: now,
+        });
     }
 
-    let price = get_quote_price(&market.oracle_type, &ctx.accounts.price_oracle).unwrap() as u64;
-    let mut total_quote = (total_base + price - 1) / price;
-    total_quote = (total_quote as f64 * 1.03).round() as u64;
+    // Overdue debts are not part of the auction and always get the full bonus
+    let risky_quote = price.base_to_quote_ceil(risky_base, &market)?;
+    let overdue_quote = price.base_to_quote_ceil(total_base - risky_base, &market)?;
+    let quote_value = risky_quote.checked_add(overdue_quote).ok_or(BalexError::MathOverflow)?;
+    let total_quote = market
+        .with_bonus_bps(risky_quote, auction_bonus_bps)?
+        .checked_add(market.with_liquidation_bonus(overdue_quote)?)
+        .ok_or(BalexError::MathOverflow)?;
+
+    // Liquidator pays insurance share of the bonus in base on top of the liquidated base
+    let insurance_base = price.quote_to_base(market.insurance_share(total_quote - quote_value), &market)?;
+    market.insurance_balance = market.insurance_balance.checked_add(insurance_base).ok_or(BalexError::MathOverflow)?;
 
     if borrower_account.quote_total < total_quote {
-        msg!("Strangely borrower doesn't have enough quote to pay, it's dangerous and means we're in a crash situation!");
-        return Err(ProgramError::Custom(0));
+        msg!("Borrower doesn't have enough quote to pay, it's a bad debt and should be resolved by resolve_bad_debt");
+        return Err(BalexError::InsufficientCollateral.into());
     }
 
     borrower_account.quote_total -= total_quote;
-    let borrower_health = get_user_health_factor(&borrower_account, &market, &ctx.accounts.price_oracle);
 
-    if borrower_health < 100 {
-        msg!("Liquidator should bring health factor more >= 100%, it's {}", borrower_health);
-        return Err(ProgramError::Custom(0));
+    // Health rules only apply when debts are liquidated because of the health
+    if risky_base > 0 {
+        if borrower_account.base_open_borrow > 0 {
+            msg!("Borrower has some borrow request order, close it first!");
+            return Err(BalexError::BorrowerHasOpenBids.into());
+        }
+
+        let new_borrower_health = get_user_health_factor(&borrower_account, &market, price, now)?;
+
+        // If borrower is too deep that close factor can't bring it back, using the whole room is enough
+        if risky_base == max_total_liquid {
+            if new_borrower_health <= borrower_health {
+                msg!("Liquidator should improve health factor, it's {} was {}", new_borrower_health, borrower_health);
+                return Err(BalexError::HealthStillTooLow.into());
+            }
+        } else if new_borrower_health < 100 {
+            msg!("Liquidator should bring health factor more >= 100%, it's {}", new_borrower_health);
+            return Err(BalexError::HealthStillTooLow.into());
+        }
+
+        refresh_liquidation_auction(borrower_account, new_borrower_health, now);
     }
 
     transfer(
@@ -175,7 +393,7 @@ pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u16>, debts_a
                 authority: ctx.accounts.liquidator.to_account_info(),
             }
         ),
-        total_base,
+        total_base + insurance_base,
     )?;
 
     transfer(
@@ -191,7 +409,271 @@ pub fn liquidate_debts(ctx: Context<LiquidateDebts>, debts_id: Vec<u16>, debts_a
         total_quote,
     )?;
 
-    msg!("Liquidated with total base {} and got back total quote {}", total_base, total_quote);
+    msg!("Liquidated with total base {} and got back total quote {}, {} base to insurance", total_base, total_quote, insurance_base);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseDebt<'info> {
+    #[account(
+        mut,
+        has_one = payer @ BalexError::InvalidDebtPayer,
+        constraint = debt.load()?.qty == 0 @ BalexError::DebtNotSettled,
+        close = payer,
+    )]
+    pub debt: AccountLoader<'info, Debt>,
+
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
+
+// Anyone can close a settled debt account, its rent goes back to whoever paid it at the fill
+pub fn close_debt(ctx: Context<CloseDebt>) -> ProgramResult {
+    let debt = ctx.accounts.debt.load()?;
+    msg!("Debt {} is closed, rent goes back to {}", debt.id, debt.payer);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Accounts)]
+pub struct UpdateLiquidationAuction<'info> {
+    #[account(mut, seeds=[&market.key().to_bytes(), &borrower_account.load()?.owner.to_bytes()], bump)]
+    pub borrower_account: AccountLoader<'info, UserAccount>,
+
+    #[account(has_one = price_oracle @ BalexError::InvalidPriceOracle, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account()]
+    pub price_oracle: AccountInfo<'info>,
+}
+
+// Anyone can open liquidation auction of an unhealthy borrower, or close it once borrower is healthy again
+pub fn update_liquidation_auction(ctx: Context<UpdateLiquidationAuction>) -> ProgramResult {
+    let borrower_account = &mut ctx.accounts.borrower_account.load_mut()?;
+    let market = ctx.accounts.market.load()?;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let price = get_quote_price(&market, &ctx.accounts.price_oracle, clock.slot)?;
+
+    let health = get_user_health_factor(&borrower_account, &market, price, now)?;
+    if refresh_liquidation_auction(borrower_account, health, now) {
+        msg!("Liquidation auction start of {} is {} with health {}", borrower_account.owner, borrower_account.liquidation_auction_start, health);
+    }
+
+    Ok(())
+}
+
+// Lender user accounts with their owners, sorted by owner
+fn get_lender_accounts<'a, 'info>(
+    market_key: &Pubkey,
+    program_id: &Pubkey,
+    lender_account_infos: &'a [AccountInfo<'info>],
+) -> Result<Vec<(&'a AccountInfo<'info>, Pubkey)>, ProgramError> {
+    let mut lender_accounts: Vec<(&AccountInfo, Pubkey)> = Vec::new();
+
+    for acc in lender_account_infos.iter() {
+        let acc_loader: AccountLoader<UserAccount> = AccountLoader::try_from(acc)?;
+        let acc_data = acc_loader.load()?;
+        if get_user_account_address(market_key, &acc_data.owner, program_id).0 != acc.key() {
+            msg!("Lender account {} doesn't belong to the market", acc.key());
+            return Err(BalexError::InvalidUserAccount.into());
+        }
+        lender_accounts.push((acc, acc_data.owner))
+    }
+
+    lender_accounts.sort_unstable_by_key(|a| a.1);
+    Ok(lender_accounts)
+}
+
+#[derive(Accounts)]
+pub struct ResolveBadDebt<'info> {
+    #[account()]
+    pub liquidator: Signer<'info>,
+
+    #[account(mut, seeds=[&market.key().to_bytes(), &borrower_account.load()?.owner.to_bytes()], bump)]
+    pub borrower_account: AccountLoader<'info, UserAccount>,
+
+    #[account(
+        mut,
+        has_one = price_oracle @ BalexError::InvalidPriceOracle,
+        has_one = base_vault @ BalexError::InvalidVault,
+        has_one = quote_vault @ BalexError::InvalidVault,
+        constraint = !market.load()?.is_paused() @ BalexError::MarketPaused,
+    )]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
+    pub market_signer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = token_base_src.mint == base_vault.mint @ BalexError::InvalidTokenAccount)]
+    pub token_base_src: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = token_quote_dest.mint == quote_vault.mint @ BalexError::InvalidTokenAccount)]
+    pub token_quote_dest: Account<'info, TokenAccount>,
+
+    #[account()]
+    pub price_oracle: AccountInfo<'info>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+// Closes all debts of an insolvent borrower. Liquidator buys all of its collateral like liquidate_debts,
+// base free of borrower and the paid base are shared pro-rata between lenders and the rest is written off.
+// Debt accounts should be given in remaining_accounts in the order of open debts of borrower, followed by lender user accounts
+pub fn resolve_bad_debt(ctx: Context<ResolveBadDebt>) -> ProgramResult {
+    let borrower_account = &mut load_user_account_mut(&ctx.accounts.borrower_account)?;
+    let market = &mut ctx.accounts.market.load_mut()?;
+    let market_key = ctx.accounts.market.key();
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let price = get_quote_price(&market, &ctx.accounts.price_oracle, clock.slot)?;
+
+    if !is_user_insolvent(&borrower_account, &market, price, now)? {
+        msg!("Borrower collateral can pay its debts");
+        return Err(BalexError::BorrowerNotInsolvent.into());
+    }
+
+    if borrower_account.base_open_borrow > 0 {
+        msg!("Borrower has some borrow request order, close it first!");
+        return Err(BalexError::BorrowerHasOpenBids.into());
+    }
+
+    let debts_cnt = borrower_account.open_debts_cnt as usize;
+    if ctx.remaining_accounts.len() < debts_cnt {
+        msg!("All {} debt accounts of borrower should be given before lender accounts", debts_cnt);
+        return Err(BalexError::DebtAccountMissing.into());
+    }
+
+    let debt_ids: Vec<u64> = (0..debts_cnt).map(|i| borrower_account.get_open_debt(i)).collect();
+    let (debt_accounts, lender_account_infos) = ctx.remaining_accounts.split_at(debts_cnt);
+    let lender_accounts = get_lender_accounts(&market_key, ctx.program_id, lender_account_infos)?;
+
+    let mut debt_loaders: Vec<AccountLoader<Debt>> = Vec::with_capacity(debts_cnt);
+    let mut debt_qtys: Vec<u64> = Vec::with_capacity(debts_cnt);
+    for (i, debt_id) in debt_ids.iter().enumerate() {
+        if get_debt_address(&market_key, *debt_id, ctx.program_id).0 != debt_accounts[i].key() {
+            msg!("Debt account {} is not debt {}", debt_accounts[i].key(), debt_id);
+            return Err(BalexError::InvalidDebtAccount.into());
+        }
+        let debt_loader: AccountLoader<Debt> = AccountLoader::try_from(&debt_accounts[i])?;
+        debt_qtys.push(debt_loader.load()?.get_debt_as_of_now(now)?);
+        debt_loaders.push(debt_loader);
+    }
+
+    let total_debt = debt_qtys.iter().try_fold(0u64, |sum, qty| sum.checked_add(*qty)).ok_or(BalexError::MathOverflow)?;
+
+    let base_free_used = borrower_account.base_free.min(total_debt);
+    let (base_paid, seized_quote) = get_collateral_seizure(&borrower_account, &market, price, total_debt - base_free_used)?;
+
+    // Insurance fund covers what collateral can't, only the rest is lost by lenders
+    let insurance_used = market.use_insurance(total_debt - base_free_used - base_paid)?;
+    let recovered = base_free_used + base_paid + insurance_used;
+    let shortfall = total_debt - recovered;
+
+    let mut distributed: u64 = 0;
+    for (i, debt_loader) in debt_loaders.iter().enumerate() {
+        let debt = &mut debt_loader.load_mut()?;
+        let debt_qty_now = debt_qtys[i];
+
+        // Last debt gets the remaining, so nothing is lost by rounding
+        let debt_recovered = if i + 1 == debts_cnt {
+            recovered - distributed
+        } else {
+            (recovered as u128 * debt_qty_now as u128 / total_debt as u128) as u64
+        };
+        distributed += debt_recovered;
+
+        let lender_id = lender_accounts.binary_search_by_key(&debt.lender, |a| a.1).map_err(|_| {
+            msg!("Lender account of debt {} is not provided", debt.id);
+            BalexError::LenderAccountMissing
+        })?;
+        let lender_account_loader: AccountLoader<UserAccount> = AccountLoader::try_from(lender_accounts[lender_id].0)?;
+        let lender = &mut load_user_account_mut(&lender_account_loader)?;
+
+        // Lender gets back its share of recovered base instead of the lent qty
+        let lent_qty = debt.qty.saturating_sub(debt.liquid_qty);
+        lender.base_locked -= lent_qty;
+        lender.base_free += debt_recovered;
+
+        borrower_account.remove_borrow(debt)?;
+        debt.qty = 0;
+        debt.liquid_qty = 0;
+        debt.accrued_interest = 0;
+        debt.timestamp = now;
+
+        market.open_debts_cnt -= 1;
+        borrower_account.remove_debt(debt.id)?;
+        lender.remove_debt(debt.id)?;
+
+        emit!(DebtWrittenOff {
+            market: market_key,
+            debt_id: debt.id,
+            lender: debt.lender,
+            borrower: debt.borrower,
+            recovered: debt_recovered,
+            loss: debt_qty_now - debt_recovered,
+            interest_rate: debt.interest_rate,
+            timestamp: now,
+        });
+    }
+
+    borrower_account.base_free -= base_free_used;
+    borrower_account.quote_total -= seized_quote;
+    market.total_bad_debt = market.total_bad_debt.checked_add(shortfall).ok_or(BalexError::MathOverflow)?;
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.token_base_src.to_account_info(),
+                to: ctx.accounts.base_vault.to_account_info(),
+                authority: ctx.accounts.liquidator.to_account_info(),
+            }
+        ),
+        base_paid,
+    )?;
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.quote_vault.to_account_info(),
+                to: ctx.accounts.token_quote_dest.to_account_info(),
+                authority: ctx.accounts.market_signer.clone(),
+            },
+            &[&[&market_key.to_bytes(), &[market.signer_bump]]]
+        ),
+        seized_quote,
+    )?;
+
+    msg!(
+        "Resolved bad debt of {} with total debt {}, recovered {} with {} from insurance and wrote off {}",
+        borrower_account.owner,
+        total_debt,
+        recovered,
+        insurance_used,
+        shortfall
+    );
+
+    emit!(BadDebtResolved {
+        market: market_key,
+        borrower: borrower_account.owner,
+        liquidator: ctx.accounts.liquidator.key(),
+        total_debt,
+        seized_quote,
+        recovered,
+        insurance_used,
+        shortfall,
+        timestamp: now,
+    });
+
+    Ok(())
+}
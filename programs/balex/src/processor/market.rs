@@ -1,9 +1,19 @@
 use anchor_lang::prelude::*;
 use pyth_client::{load_price};
-use anchor_spl::token::TokenAccount;
-use crate::state::{LexMarket, StubPrice, OracleType, CALLBACK_ID_LEN, CALLBACK_INFO_LEN};
+use anchor_spl::token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use agnostic_orderbook::state::EventQueueHeader;
+use crate::error::BalexError;
+use crate::events::{InsuranceDeposit, InsuranceRetired};
+use crate::state::{
+    LexMarket, MarketParams, MarketStatus, StubPrice, OracleType, CALLBACK_ID_LEN, CALLBACK_INFO_LEN,
+    DEFAULT_OVER_COLLATERAL_PERCENT, DEFAULT_LIQUIDATION_BONUS_PERCENT, DEFAULT_CLOSE_FACTOR_PERCENT,
+    DEFAULT_MIN_ORDER_SIZE, DEFAULT_MAX_INTEREST_RATE, MAX_INTEREST_RATE_LIMIT, DEFAULT_MAX_CONFIDENCE_BPS,
+    DEFAULT_MAX_PRICE_STALENESS_SLOTS, DEFAULT_PROTOCOL_FEE_PERCENT, DEFAULT_CRANK_FEE, DEFAULT_GRACE_PERIOD,
+    DEFAULT_INSURANCE_PERCENT, DEFAULT_LIQUIDATION_AUCTION_DURATION,
+};
 
 #[derive(Accounts)]
+#[instruction(signer_bump: u8)]
 pub struct InitializeMarket<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
@@ -11,10 +21,25 @@ pub struct InitializeMarket<'info> {
     #[account(zero)]
     pub market: AccountLoader<'info, LexMarket>,
 
+    #[account(seeds=[&market.key().to_bytes()], bump=signer_bump)]
+    pub market_signer: AccountInfo<'info>,
+
     #[account()]
-    pub base_vault: Account<'info, TokenAccount>,
+    pub base_mint: Account<'info, Mint>,
 
     #[account()]
+    pub quote_mint: Account<'info, Mint>,
+
+    #[account(
+        constraint = base_vault.mint == base_mint.key() @ BalexError::InvalidTokenAccount,
+        constraint = base_vault.owner == market_signer.key() @ BalexError::InvalidTokenAccount,
+    )]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = quote_vault.mint == quote_mint.key() @ BalexError::InvalidTokenAccount,
+        constraint = quote_vault.owner == market_signer.key() @ BalexError::InvalidTokenAccount,
+    )]
     pub quote_vault: Account<'info, TokenAccount>,
 
     #[account(mut)]
@@ -39,22 +64,41 @@ pub struct InitializeMarket<'info> {
 pub fn initialize_market(
     ctx: Context<InitializeMarket>,
     signer_bump: u8,
-    base_mint: Pubkey,
-    quote_mint: Pubkey,
     oracle_type: OracleType,
+    loan_term: u64,
 ) -> ProgramResult {
     let mut market = ctx.accounts.market.load_init()?;
 
     market.admin = ctx.accounts.admin.key();
-    market.base_mint = base_mint;
-    market.quote_mint = quote_mint;
+    market.risk_admin = ctx.accounts.admin.key();
+    market.fee_admin = ctx.accounts.admin.key();
+    market.guardian = ctx.accounts.admin.key();
+    market.status = MarketStatus::Active;
+    market.base_mint = ctx.accounts.base_mint.key();
+    market.quote_mint = ctx.accounts.quote_mint.key();
+    market.base_decimals = ctx.accounts.base_mint.decimals;
+    market.quote_decimals = ctx.accounts.quote_mint.decimals;
 
     market.base_vault = ctx.accounts.base_vault.key();
     market.quote_vault = ctx.accounts.quote_vault.key();
 
     market.signer_bump = signer_bump;
+    market.loan_term = loan_term;
 
-    market.over_collateral_percent = 50;
+    market.set_params(&MarketParams {
+        over_collateral_percent: DEFAULT_OVER_COLLATERAL_PERCENT,
+        liquidation_bonus_percent: DEFAULT_LIQUIDATION_BONUS_PERCENT,
+        close_factor_percent: DEFAULT_CLOSE_FACTOR_PERCENT,
+        min_order_size: DEFAULT_MIN_ORDER_SIZE,
+        max_interest_rate: DEFAULT_MAX_INTEREST_RATE,
+        max_confidence_bps: DEFAULT_MAX_CONFIDENCE_BPS,
+        max_price_staleness_slots: DEFAULT_MAX_PRICE_STALENESS_SLOTS,
+        protocol_fee_percent: DEFAULT_PROTOCOL_FEE_PERCENT,
+        crank_fee: DEFAULT_CRANK_FEE,
+        grace_period: DEFAULT_GRACE_PERIOD,
+        insurance_percent: DEFAULT_INSURANCE_PERCENT,
+        liquidation_auction_duration: DEFAULT_LIQUIDATION_AUCTION_DURATION,
+    });
 
     // TODO: More check on oracle to be from correct program
     match oracle_type {
@@ -78,13 +122,15 @@ pub fn initialize_market(
 
     market.orderbook = ctx.accounts.orderbook.key();
 
+    msg!("Loan term is {} seconds", loan_term);
+
     let invoke_params = agnostic_orderbook::instruction::create_market::Params {
         caller_authority: ctx.program_id.to_bytes(),
         callback_info_len: CALLBACK_INFO_LEN,
         callback_id_len: CALLBACK_ID_LEN,
         min_base_order_size: 1,
         tick_size: 1,
-        cranker_reward: 0,
+        cranker_reward: 0, // Crank fee is collected and paid by balex itself
     };
 
     let invoke_accounts = agnostic_orderbook::instruction::create_market::Accounts {
@@ -99,8 +145,400 @@ pub fn initialize_market(
         invoke_params,
     ) {
         msg!("{}", error);
-        return Err(ProgramError::InvalidAccountData);
+        return Err(BalexError::OrderbookError.into());
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketParams<'info> {
+    #[account()]
+    pub risk_admin: Signer<'info>,
+
+    #[account(mut, has_one=risk_admin @ BalexError::Unauthorized)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+pub fn update_market_params(ctx: Context<UpdateMarketParams>, params: MarketParams) -> ProgramResult {
+    if params.over_collateral_percent == 0
+        || params.close_factor_percent == 0
+        || params.close_factor_percent > 100
+        || params.liquidation_bonus_percent as u64 * 2 >= params.over_collateral_percent as u64
+        || params.min_order_size == 0
+        || params.max_interest_rate > MAX_INTEREST_RATE_LIMIT
+        || params.max_confidence_bps == 0
+        || params.max_price_staleness_slots == 0
+        || params.protocol_fee_percent > 100
+        || params.insurance_percent > 100
+    {
+        msg!("Invalid market params");
+        return Err(BalexError::InvalidMarketParams.into());
+    }
+
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.set_params(&params);
+
+    msg!(
+        "Market params updated: over collateral {}% liquidation bonus {}% close factor {}% min order size {} max interest rate {} max confidence {}bps max staleness {} slots protocol fee {}% crank fee {} lamports grace period {}s insurance {}% liquidation auction {}s",
+        params.over_collateral_percent,
+        params.liquidation_bonus_percent,
+        params.close_factor_percent,
+        params.min_order_size,
+        params.max_interest_rate,
+        params.max_confidence_bps,
+        params.max_price_staleness_slots,
+        params.protocol_fee_percent,
+        params.crank_fee,
+        params.grace_period,
+        params.insurance_percent,
+        params.liquidation_auction_duration
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account()]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+// Risk admin can set any status, guardian can only pause the market
+pub fn set_market_status(ctx: Context<SetMarketStatus>, status: MarketStatus) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let authority = ctx.accounts.authority.key();
+
+    if authority != market.risk_admin && !(authority == market.guardian && status == MarketStatus::Paused) {
+        msg!("{} can't change market status", authority);
+        return Err(BalexError::Unauthorized.into());
+    }
+
+    market.status = status;
+
+    msg!("Market status is {}", match status {
+        MarketStatus::Active => "active",
+        MarketStatus::ReduceOnly => "reduce-only",
+        MarketStatus::Paused => "paused",
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRoles<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one=admin @ BalexError::Unauthorized)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+pub fn set_roles(ctx: Context<SetRoles>, risk_admin: Pubkey, fee_admin: Pubkey, guardian: Pubkey) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.risk_admin = risk_admin;
+    market.fee_admin = fee_admin;
+    market.guardian = guardian;
+
+    msg!("Market roles are risk admin {} fee admin {} guardian {}", risk_admin, fee_admin, guardian);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one=admin @ BalexError::Unauthorized)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+// Admin is only handed over once the new admin accepts it, so it can't be given to a wrong key
+pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.pending_admin = new_admin;
+
+    msg!("Proposed {} as admin of the market", new_admin);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account()]
+    pub pending_admin: Signer<'info>,
+
+    #[account(mut, has_one=pending_admin @ BalexError::Unauthorized)]
+    pub market: AccountLoader<'info, LexMarket>,
+}
+
+pub fn accept_admin(ctx: Context<AcceptAdmin>) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.admin = market.pending_admin;
+    market.pending_admin = Pubkey::default();
+
+    msg!("{} is admin of the market", market.admin);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimFees<'info> {
+    #[account()]
+    pub fee_admin: Signer<'info>,
+
+    #[account(mut, has_one=fee_admin @ BalexError::Unauthorized, has_one=base_vault, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
+    pub market_signer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = token_dest.mint == base_vault.mint @ BalexError::InvalidTokenAccount)]
+    pub token_dest: Account<'info, TokenAccount>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn claim_fees(ctx: Context<ClaimFees>) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let fee_balance = market.fee_balance;
+    market.fee_balance = 0;
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.base_vault.to_account_info(),
+                to: ctx.accounts.token_dest.to_account_info(),
+                authority: ctx.accounts.market_signer.clone(),
+            },
+            &[&[&ctx.accounts.market.key().to_bytes(), &[market.signer_bump]]]
+        ),
+        fee_balance,
+    )?;
+
+    msg!(
+        "Claimed {} fees, total fees {} of total interest paid {}",
+        fee_balance,
+        market.total_fees,
+        market.total_interest_paid
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DepositInsurance<'info> {
+    #[account()]
+    pub depositor: Signer<'info>,
+
+    #[account(mut, has_one=base_vault @ BalexError::InvalidVault, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = token_src.mint == base_vault.mint @ BalexError::InvalidTokenAccount)]
+    pub token_src: Account<'info, TokenAccount>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+// Anyone can top up insurance fund of the market with base
+pub fn deposit_insurance(ctx: Context<DepositInsurance>, amount: u64) -> ProgramResult {
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.token_src.to_account_info(),
+                to: ctx.accounts.base_vault.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            }
+        ),
+        amount,
+    )?;
+
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.insurance_balance = market.insurance_balance.checked_add(amount).ok_or(BalexError::MathOverflow)?;
+
+    msg!("Deposited {} to insurance, balance is {}", amount, market.insurance_balance);
+
+    emit!(InsuranceDeposit {
+        market: ctx.accounts.market.key(),
+        depositor: ctx.accounts.depositor.key(),
+        amount,
+        insurance_balance: market.insurance_balance,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RetireInsurance<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ BalexError::Unauthorized,
+        has_one = base_vault @ BalexError::InvalidVault,
+        constraint = market.load()?.is_paused() @ BalexError::MarketNotPaused,
+    )]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
+    pub market_signer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = token_dest.mint == base_vault.mint @ BalexError::InvalidTokenAccount,
+        constraint = token_dest.owner == admin.key() @ BalexError::InvalidTokenAccount,
+    )]
+    pub token_dest: Account<'info, TokenAccount>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+// Winds down insurance fund of a paused market to admin so the market can be closed, only once no debt is left to be covered by it
+pub fn retire_insurance(ctx: Context<RetireInsurance>) -> ProgramResult {
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    if market.open_debts_cnt > 0 {
+        msg!("Market has {} open debts", market.open_debts_cnt);
+        return Err(BalexError::MarketNotEmpty.into());
+    }
+
+    let amount = market.insurance_balance;
+    market.insurance_balance = 0;
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.base_vault.to_account_info(),
+                to: ctx.accounts.token_dest.to_account_info(),
+                authority: ctx.accounts.market_signer.clone(),
+            },
+            &[&[&ctx.accounts.market.key().to_bytes(), &[market.signer_bump]]]
+        ),
+        amount,
+    )?;
+
+    msg!("Retired {} of insurance, total insurance paid {}", amount, market.total_insurance_paid);
+
+    emit!(InsuranceRetired {
+        market: ctx.accounts.market.key(),
+        admin: ctx.accounts.admin.key(),
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one=admin @ BalexError::Unauthorized, has_one=orderbook, has_one=base_vault, has_one=quote_vault, close=admin)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(seeds=[&market.key().to_bytes()], bump=market.load()?.signer_bump)]
+    pub market_signer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub base_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub event_queue: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub orderbook: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub asks: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub bids: AccountInfo<'info>,
+
+    #[account()]
+    pub token_program: Program<'info, Token>,
+}
+
+// Closes market with its orderbook accounts and vaults, all lamports go to admin
+pub fn close_market(ctx: Context<CloseMarket>) -> ProgramResult {
+    let (open_debts_cnt, signer_bump) = {
+        let market = ctx.accounts.market.load()?;
+        (market.open_debts_cnt, market.signer_bump)
+    };
+
+    if open_debts_cnt > 0 {
+        msg!("Market has {} open debts", open_debts_cnt);
+        return Err(BalexError::MarketNotEmpty.into());
+    }
+
+    if ctx.accounts.base_vault.amount > 0 || ctx.accounts.quote_vault.amount > 0 {
+        msg!("Market vaults are not empty, base {} quote {}", ctx.accounts.base_vault.amount, ctx.accounts.quote_vault.amount);
+        return Err(BalexError::MarketNotEmpty.into());
+    }
+
+    let event_queue_header =
+        EventQueueHeader::deserialize(&mut (&ctx.accounts.event_queue.data.borrow() as &[u8]))?;
+    if event_queue_header.count > 0 {
+        msg!("Event queue has {} events, consume them first", event_queue_header.count);
+        return Err(BalexError::EventQueueNotEmpty.into());
+    }
+
+    let aob_accounts = agnostic_orderbook::instruction::close_market::Accounts {
+        market: &ctx.accounts.orderbook,
+        event_queue: &ctx.accounts.event_queue,
+        bids: &ctx.accounts.bids,
+        asks: &ctx.accounts.asks,
+        authority: &ctx.accounts.admin.to_account_info(), // No impact with AOB as a lib
+        lamports_target_account: &ctx.accounts.admin.to_account_info(),
+    };
+
+    if let Err(error) = agnostic_orderbook::instruction::close_market::process(
+        ctx.program_id,
+        aob_accounts,
+        agnostic_orderbook::instruction::close_market::Params {},
+    ) {
+        msg!("{}", error);
+        return Err(BalexError::OrderbookError.into());
     }
 
+    let market_key = ctx.accounts.market.key().to_bytes();
+    for vault in [&ctx.accounts.base_vault, &ctx.accounts.quote_vault].iter() {
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: vault.to_account_info(),
+                destination: ctx.accounts.admin.to_account_info(),
+                authority: ctx.accounts.market_signer.clone(),
+            },
+            &[&[&market_key, &[signer_bump]]],
+        ))?;
+    }
+
+    msg!("Market {} is closed", ctx.accounts.market.key());
+
     Ok(())
 }
@@ -1,7 +1,9 @@
 use crate::create_debt;
-use crate::state::{get_max_borrow_qty, get_user_health_factor, LexMarket, UserAccount};
+use crate::create_debt_account;
+use crate::error::BalexError;
+use crate::events::{OrderCancelled, OrderPlaced};
+use crate::state::{get_debt_address, get_max_borrow_qty, get_quote_price, get_user_health_factor, load_user_account_mut, LexMarket, OrderType, SelfTradeBehavior, UserAccount, UserAccountMut};
 use crate::CALLBACK_INFO_LEN;
-use crate::USER_OPEN_ORDERS_SIZE;
 use agnostic_orderbook::state::read_register;
 use agnostic_orderbook::state::Event;
 use agnostic_orderbook::state::EventQueue;
@@ -9,6 +11,8 @@ use agnostic_orderbook::state::EventQueueHeader;
 use agnostic_orderbook::state::OrderSummary;
 use agnostic_orderbook::state::Side;
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::system_instruction;
 use std::rc::Rc;
 
 #[derive(Accounts)]
@@ -20,7 +24,7 @@ pub struct NewOrder<'info> {
     #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
     pub user_account: AccountLoader<'info, UserAccount>,
 
-    #[account(mut, has_one=orderbook)]
+    #[account(mut, has_one=orderbook, has_one = price_oracle @ BalexError::InvalidPriceOracle, constraint = market.load()?.is_active() @ BalexError::MarketNotActive)]
     pub market: AccountLoader<'info, LexMarket>,
 
     #[account(mut)]
@@ -39,82 +43,208 @@ pub struct NewOrder<'info> {
     pub price_oracle: AccountInfo<'info>,
 
     #[account()]
-    system_program: Program<'info, System>, // Later could be used for paying rewards and maybe transaction fee
+    system_program: Program<'info, System>, // Used for paying crank fee
 }
 
-// rate is 32bit fixed point float
+// interest_rate is integer percent per hour, accrued linearly with INTEREST_RATE_DENOMINATOR.
+// Orders take loan_term of the market, as each market is a single term
 pub fn new_order(
     ctx: Context<NewOrder>,
     _bump: u8,
     side_num: u8,
     interest_rate: u64,
     qty: u64,
+    expiry_timestamp: i64,
+    order_type: OrderType,
+    self_trade_behavior: SelfTradeBehavior,
+    client_order_id: u64,
 ) -> ProgramResult {
     // TODO: Make side_num enum. Giving instant enum didn't work as anchor couldn't generate IDL for it
     let side = match side_num {
         0 => Side::Bid,
         1 => Side::Ask,
-        _ => return Err(ProgramError::InvalidArgument),
+        _ => return Err(BalexError::InvalidOrderSide.into()),
     };
 
-    let mut user_account = ctx.accounts.user_account.load_mut()?;
-    let market = ctx.accounts.market.load()?;
+    place_order(
+        ctx.program_id,
+        &ctx.accounts,
+        side,
+        interest_rate,
+        qty,
+        expiry_timestamp,
+        order_type,
+        self_trade_behavior,
+        client_order_id,
+    )
+}
+
+// Cancels the order and places a new one on the same side in one instruction, so quotes don't disappear in between
+pub fn replace_order(
+    ctx: Context<NewOrder>,
+    _bump: u8,
+    order_id: u128,
+    interest_rate: u64,
+    qty: u64,
+    expiry_timestamp: i64,
+    order_type: OrderType,
+    self_trade_behavior: SelfTradeBehavior,
+    client_order_id: u64,
+) -> ProgramResult {
+    {
+        let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
+        cancel_user_order(
+            ctx.program_id,
+            ctx.accounts.market.key(),
+            &ctx.accounts.orderbook,
+            &ctx.accounts.event_queue,
+            &ctx.accounts.bids,
+            &ctx.accounts.asks,
+            &ctx.accounts.system_program.to_account_info(),
+            &mut user_account,
+            order_id,
+        )?;
+    }
+
+    place_order(
+        ctx.program_id,
+        &ctx.accounts,
+        agnostic_orderbook::state::get_side_from_order_id(order_id),
+        interest_rate,
+        qty,
+        expiry_timestamp,
+        order_type,
+        self_trade_behavior,
+        client_order_id,
+    )
+}
+
+fn place_order<'info>(
+    program_id: &Pubkey,
+    accounts: &NewOrder<'info>,
+    side: Side,
+    interest_rate: u64,
+    qty: u64,
+    expiry_timestamp: i64,
+    order_type: OrderType,
+    self_trade_behavior: SelfTradeBehavior,
+    client_order_id: u64,
+) -> ProgramResult {
+    // Crank fee is kept in market account and paid to crankers consuming the events
+    let crank_fee = accounts.market.load()?.crank_fee;
+    if crank_fee > 0 {
+        invoke(
+            &system_instruction::transfer(accounts.owner.key, &accounts.market.key(), crank_fee),
+            &[
+                accounts.owner.to_account_info(),
+                accounts.market.to_account_info(),
+                accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    let mut user_account = load_user_account_mut(&accounts.user_account)?;
+    let mut market = accounts.market.load_mut()?;
+    market.crank_fee_budget += crank_fee;
+
+    if qty < market.min_order_size {
+        msg!("Minimum order size is {}", market.min_order_size);
+        return Err(BalexError::OrderSizeTooSmall.into());
+    }
+
+    if interest_rate > market.max_interest_rate {
+        msg!("Maximum interest rate is {}", market.max_interest_rate);
+        return Err(BalexError::InterestRateTooHigh.into());
+    }
+
+    let clock = Clock::get()?;
+
+    // Zero means good till cancel, expired orders can be purged by anyone
+    if expiry_timestamp != 0 && expiry_timestamp <= clock.unix_timestamp {
+        msg!("Expiry timestamp {} is already passed", expiry_timestamp);
+        return Err(BalexError::InvalidExpiryTimestamp.into());
+    }
+
+    // Zero means no client order id, others should be unique among open orders of the user
+    if client_order_id != 0 && user_account.find_order_by_client_id(client_order_id).is_some() {
+        msg!("Client order id {} is already used by an open order", client_order_id);
+        return Err(BalexError::DuplicateClientOrderId.into());
+    }
 
     match side {
         Side::Ask => {
             if qty > user_account.base_free {
-                return Err(ProgramError::InvalidInstructionData);
+                return Err(BalexError::InsufficientBaseFree.into());
             }
         }
         Side::Bid => {
+            let price = get_quote_price(&market, &accounts.price_oracle, clock.slot)?;
             let max_borrow_qty =
-                get_max_borrow_qty(&user_account, &market, &ctx.accounts.price_oracle);
+                get_max_borrow_qty(&user_account, &market, price, clock.unix_timestamp)?;
             msg!("Max borrow amount is {}", max_borrow_qty);
             if qty > max_borrow_qty {
-                return Err(ProgramError::InsufficientFunds);
+                return Err(BalexError::BorrowLimitExceeded.into());
             }
         }
     };
 
+    let (post_only, post_allowed) = match order_type {
+        OrderType::Limit => (false, true),
+        OrderType::PostOnly => (true, true),
+        OrderType::ImmediateOrCancel | OrderType::FillOrKill => (false, false),
+    };
+
+    let aob_self_trade_behavior = match self_trade_behavior {
+        SelfTradeBehavior::DecrementTake => agnostic_orderbook::state::SelfTradeBehavior::DecrementTake,
+        SelfTradeBehavior::CancelProvide => agnostic_orderbook::state::SelfTradeBehavior::CancelProvide,
+        SelfTradeBehavior::AbortTransaction => agnostic_orderbook::state::SelfTradeBehavior::AbortTransaction,
+    };
+
     let aob_param = agnostic_orderbook::instruction::new_order::Params {
         max_base_qty: qty,
         max_quote_qty: u64::MAX,
         limit_price: interest_rate,
         side: side,
         match_limit: 10000,
-        post_only: false,
-        post_allowed: true,
-        callback_info: ctx.accounts.user_account.key().to_bytes().to_vec(),
-        self_trade_behavior: agnostic_orderbook::state::SelfTradeBehavior::AbortTransaction,
+        post_only,
+        post_allowed,
+        callback_info: accounts.user_account.key().to_bytes().to_vec(),
+        self_trade_behavior: aob_self_trade_behavior,
     };
 
     let aob_accounts = agnostic_orderbook::instruction::new_order::Accounts {
-        market: &ctx.accounts.orderbook,
-        asks: &ctx.accounts.asks,
-        bids: &ctx.accounts.bids,
-        event_queue: &ctx.accounts.event_queue,
-        authority: &ctx.accounts.system_program.to_account_info(),
+        market: &accounts.orderbook,
+        asks: &accounts.asks,
+        bids: &accounts.bids,
+        event_queue: &accounts.event_queue,
+        authority: &accounts.system_program.to_account_info(),
     };
 
     if let Err(err) =
-        agnostic_orderbook::instruction::new_order::process(ctx.program_id, aob_accounts, aob_param)
+        agnostic_orderbook::instruction::new_order::process(program_id, aob_accounts, aob_param)
     {
         msg!("{}", err);
         return Err(err);
     }
 
-    let order_summary: OrderSummary = read_register(&ctx.accounts.event_queue).unwrap().unwrap();
-
-    if let Some(order_id) = order_summary.posted_order_id {
-        let open_orders_cnt = user_account.open_orders_cnt as usize;
+    let order_summary: OrderSummary = read_register(&accounts.event_queue)
+        .ok()
+        .flatten()
+        .ok_or(BalexError::OrderSummaryMissing)?;
 
-        if open_orders_cnt == USER_OPEN_ORDERS_SIZE {
-            msg!("Max open orders reached.");
-            return Err(ProgramError::AccountDataTooSmall);
+    match order_type {
+        OrderType::PostOnly if order_summary.posted_order_id.is_none() => {
+            return Err(BalexError::PostOnlyWouldMatch.into());
         }
+        OrderType::FillOrKill if order_summary.total_base_qty < qty => {
+            msg!("Only {} of {} could be filled", order_summary.total_base_qty, qty);
+            return Err(BalexError::OrderNotFilled.into());
+        }
+        _ => {}
+    }
 
-        user_account.open_orders[open_orders_cnt] = order_id;
-        user_account.open_orders_cnt += 1;
+    if let Some(order_id) = order_summary.posted_order_id {
+        user_account.add_order(order_id, expiry_timestamp, client_order_id)?;
     }
 
     match side {
@@ -127,6 +257,18 @@ pub fn new_order(
         }
     }
 
+    emit!(OrderPlaced {
+        market: accounts.market.key(),
+        user: user_account.owner,
+        order_id: order_summary.posted_order_id.unwrap_or(0),
+        client_order_id,
+        side: side as u8,
+        interest_rate,
+        qty: order_summary.total_base_qty,
+        expiry_timestamp,
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -159,18 +301,113 @@ pub struct CancelMyOrder<'info> {
 }
 
 pub fn cancel_my_order(ctx: Context<CancelMyOrder>, _bump: u8, order_id: u128) -> ProgramResult {
+    let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
+
+    cancel_user_order(
+        ctx.program_id,
+        ctx.accounts.market.key(),
+        &ctx.accounts.orderbook,
+        &ctx.accounts.event_queue,
+        &ctx.accounts.bids,
+        &ctx.accounts.asks,
+        &ctx.accounts.system_program.to_account_info(),
+        &mut user_account,
+        order_id,
+    )
+}
+
+pub fn cancel_by_client_order_id(ctx: Context<CancelMyOrder>, _bump: u8, client_order_id: u64) -> ProgramResult {
+    let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
+
+    let order_id = match client_order_id {
+        0 => None,
+        _ => user_account.find_order_by_client_id(client_order_id),
+    }.ok_or_else(|| {
+        msg!("No open order with client order id {}", client_order_id);
+        BalexError::OrderNotFound
+    })?;
+
+    cancel_user_order(
+        ctx.program_id,
+        ctx.accounts.market.key(),
+        &ctx.accounts.orderbook,
+        &ctx.accounts.event_queue,
+        &ctx.accounts.bids,
+        &ctx.accounts.asks,
+        &ctx.accounts.system_program.to_account_info(),
+        &mut user_account,
+        order_id,
+    )
+}
+
+// Cancels open orders of the user, only the given side if side_num is set
+pub fn cancel_all_orders(ctx: Context<CancelMyOrder>, _bump: u8, side_num: Option<u8>) -> ProgramResult {
+    let side_filter = match side_num {
+        None => None,
+        Some(0) => Some(Side::Bid),
+        Some(1) => Some(Side::Ask),
+        _ => return Err(BalexError::InvalidOrderSide.into()),
+    };
+
+    let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
+
+    let order_ids: Vec<u128> = (0..user_account.open_orders_cnt as usize)
+        .map(|i| user_account.get_open_order(i))
+        .filter(|&order_id| match (side_filter, agnostic_orderbook::state::get_side_from_order_id(order_id)) {
+            (None, _) | (Some(Side::Bid), Side::Bid) | (Some(Side::Ask), Side::Ask) => true,
+            _ => false,
+        })
+        .collect();
+
+    let mut cancelled_cnt = 0;
+    for order_id in order_ids.iter() {
+        // Orders which are filled but not consumed yet aren't in the orderbook anymore
+        if cancel_user_order(
+            ctx.program_id,
+            ctx.accounts.market.key(),
+            &ctx.accounts.orderbook,
+            &ctx.accounts.event_queue,
+            &ctx.accounts.bids,
+            &ctx.accounts.asks,
+            &ctx.accounts.system_program.to_account_info(),
+            &mut user_account,
+            *order_id,
+        ).is_ok() {
+            cancelled_cnt += 1;
+        } else {
+            msg!("Order {} is skipped", order_id);
+        }
+    }
+
+    msg!("Cancelled {} of {} orders", cancelled_cnt, order_ids.len());
+
+    Ok(())
+}
+
+// Cancels the order in the orderbook and removes its open qty from the user account
+fn cancel_user_order<'info>(
+    program_id: &Pubkey,
+    market_key: Pubkey,
+    orderbook: &AccountInfo<'info>,
+    event_queue: &AccountInfo<'info>,
+    bids: &AccountInfo<'info>,
+    asks: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    user_account: &mut UserAccountMut,
+    order_id: u128,
+) -> ProgramResult {
     let aob_params = agnostic_orderbook::instruction::cancel_order::Params { order_id: order_id };
 
     let aob_accounts = agnostic_orderbook::instruction::cancel_order::Accounts {
-        market: &ctx.accounts.orderbook,
-        event_queue: &ctx.accounts.event_queue,
-        bids: &ctx.accounts.bids,
-        asks: &ctx.accounts.asks,
-        authority: &ctx.accounts.system_program.to_account_info(),
+        market: orderbook,
+        event_queue: event_queue,
+        bids: bids,
+        asks: asks,
+        authority: authority,
     };
 
     if let Err(err) = agnostic_orderbook::instruction::cancel_order::process(
-        ctx.program_id,
+        program_id,
         aob_accounts,
         aob_params,
     ) {
@@ -179,12 +416,15 @@ pub fn cancel_my_order(ctx: Context<CancelMyOrder>, _bump: u8, order_id: u128) -
     }
 
     let side = agnostic_orderbook::state::get_side_from_order_id(order_id);
-    let order_summary: OrderSummary = read_register(&ctx.accounts.event_queue).unwrap().unwrap();
-    let mut user_account = ctx.accounts.user_account.load_mut()?;
+    let order_summary: OrderSummary = read_register(event_queue)
+        .ok()
+        .flatten()
+        .ok_or(BalexError::OrderSummaryMissing)?;
 
     match side {
         Side::Ask => {
             user_account.base_open_lend -= order_summary.total_base_qty;
+            user_account.base_free += order_summary.total_base_qty;
         },
         Side::Bid => {
             user_account.base_open_borrow -= order_summary.total_base_qty;
@@ -193,6 +433,80 @@ pub fn cancel_my_order(ctx: Context<CancelMyOrder>, _bump: u8, order_id: u128) -
 
     user_account.remove_order(order_id)?;
 
+    emit!(OrderCancelled {
+        market: market_key,
+        user: user_account.owner,
+        order_id,
+        side: side as u8,
+        qty: order_summary.total_base_qty,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+// Anyone can cancel orders of a user which are passed their expiry timestamp
+#[derive(Accounts)]
+pub struct PurgeExpiredOrders<'info> {
+    #[account(mut, seeds=[&market.key().to_bytes(), &user_account.load()?.owner.to_bytes()], bump)]
+    pub user_account: AccountLoader<'info, UserAccount>,
+
+    #[account(mut, has_one=orderbook)]
+    pub market: AccountLoader<'info, LexMarket>,
+
+    #[account(mut)]
+    pub event_queue: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub orderbook: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub asks: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub bids: AccountInfo<'info>,
+
+    #[account()]
+    system_program: Program<'info, System>,
+}
+
+pub fn purge_expired_orders(ctx: Context<PurgeExpiredOrders>, max_orders: u16) -> ProgramResult {
+    let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
+    let now = Clock::get()?.unix_timestamp;
+
+    let expired_orders: Vec<u128> = (0..user_account.open_orders_cnt as usize)
+        .filter(|&i| user_account.is_open_order_expired(i, now))
+        .map(|i| user_account.get_open_order(i))
+        .take(max_orders as usize)
+        .collect();
+
+    if expired_orders.is_empty() {
+        msg!("User has no expired order");
+        return Err(BalexError::NoExpiredOrders.into());
+    }
+
+    let mut purged_cnt = 0;
+    for order_id in expired_orders.iter() {
+        // Orders which are filled but not consumed yet aren't in the orderbook anymore
+        if cancel_user_order(
+            ctx.program_id,
+            ctx.accounts.market.key(),
+            &ctx.accounts.orderbook,
+            &ctx.accounts.event_queue,
+            &ctx.accounts.bids,
+            &ctx.accounts.asks,
+            &ctx.accounts.system_program.to_account_info(),
+            &mut user_account,
+            *order_id,
+        ).is_ok() {
+            purged_cnt += 1;
+        } else {
+            msg!("Order {} is skipped", order_id);
+        }
+    }
+
+    msg!("Purged {} of {} expired orders of {}", purged_cnt, expired_orders.len(), user_account.owner);
+
     Ok(())
 }
 
@@ -205,7 +519,7 @@ pub struct CancelRiskyOrder<'info> {
     #[account(mut, seeds=[&market.key().to_bytes(), &owner.key().to_bytes()], bump=_bump)]
     pub user_account: AccountLoader<'info, UserAccount>,
 
-    #[account(mut, has_one=orderbook)]
+    #[account(mut, has_one=orderbook, has_one = price_oracle @ BalexError::InvalidPriceOracle)]
     pub market: AccountLoader<'info, LexMarket>,
 
     #[account(mut)]
@@ -235,18 +549,20 @@ pub fn cancel_risky_order(
     let side = agnostic_orderbook::state::get_side_from_order_id(order_id);
 
     if let Side::Ask = side {
-        return Err(ProgramError::InvalidInstructionData);
+        return Err(BalexError::RiskyOrderNotBid.into());
     }
 
-    let mut user_account = ctx.accounts.user_account.load_mut()?;
+    let mut user_account = load_user_account_mut(&ctx.accounts.user_account)?;
     let market = ctx.accounts.market.load()?;
 
-    let health_factor = get_user_health_factor(&user_account, &market, &ctx.accounts.price_oracle);
+    let clock = Clock::get()?;
+    let price = get_quote_price(&market, &ctx.accounts.price_oracle, clock.slot)?;
+    let health_factor = get_user_health_factor(&user_account, &market, price, clock.unix_timestamp)?;
     msg!("Health factor: {}", health_factor);
 
     if health_factor >= 100 {
         msg!("User is good shape with good health factor");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(BalexError::UserHealthy.into());
     }
 
     let aob_params = agnostic_orderbook::instruction::cancel_order::Params { order_id: order_id };
@@ -268,12 +584,24 @@ pub fn cancel_risky_order(
         return Err(err);
     }
 
-    let order_summary: OrderSummary = read_register(&ctx.accounts.event_queue).unwrap().unwrap();
+    let order_summary: OrderSummary = read_register(&ctx.accounts.event_queue)
+        .ok()
+        .flatten()
+        .ok_or(BalexError::OrderSummaryMissing)?;
 
     user_account.base_open_borrow -= order_summary.total_base_qty;
 
     user_account.remove_order(order_id)?;
 
+    emit!(OrderCancelled {
+        market: ctx.accounts.market.key(),
+        user: user_account.owner,
+        order_id,
+        side: side as u8,
+        qty: order_summary.total_base_qty,
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -281,7 +609,7 @@ pub fn cancel_risky_order(
 #[derive(Accounts)]
 #[instruction(_bump: u8)]
 pub struct ConsumerOrderEvents<'info> {
-    #[account(mut, has_one=orderbook)]
+    #[account(mut, has_one=orderbook, constraint = !market.load()?.is_paused() @ BalexError::MarketPaused)]
     pub market: AccountLoader<'info, LexMarket>, // Will be used later to aggregate total informations
 
     #[account(mut)]
@@ -290,8 +618,16 @@ pub struct ConsumerOrderEvents<'info> {
     #[account(mut)]
     pub orderbook: AccountInfo<'info>,
 
+    // Pays for debt accounts created by fill events
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // Receives crank fee of the consumed events in lamports
+    #[account(mut)]
+    pub reward_target: AccountInfo<'info>,
+
     #[account()]
-    pub system_program: Program<'info, System>, // Later could be used for paying rewards and maybe transaction fee
+    pub system_program: Program<'info, System>,
 }
 
 
@@ -299,6 +635,7 @@ pub fn consume_order_events(
     ctx: Context<ConsumerOrderEvents>,
     max_iterations: u64,
 ) -> ProgramResult {
+    // User accounts of events and debt accounts of fill events (by order of next_debt_id)
     let user_accounts: &[AccountInfo] = ctx.remaining_accounts;
 
     if user_accounts.is_empty() {
@@ -308,6 +645,7 @@ pub fn consume_order_events(
 
     let event_queue_header =
         EventQueueHeader::deserialize(&mut (&ctx.accounts.event_queue.data.borrow() as &[u8]))?;
+    let queue_len = event_queue_header.count;
     let event_queue = EventQueue::new(
         event_queue_header,
         Rc::clone(&ctx.accounts.event_queue.data),
@@ -317,7 +655,14 @@ pub fn consume_order_events(
     let mut total_iterations = 0;
 
     for event in event_queue.iter().take(max_iterations as usize) {
-        if let Err(err) = consume_event(user_accounts, event, &ctx.accounts.market) {
+        if let Err(err) = consume_event(
+            ctx.program_id,
+            user_accounts,
+            event,
+            &ctx.accounts.market,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        ) {
             msg!("{}", err);
             break;
         }
@@ -326,7 +671,7 @@ pub fn consume_order_events(
 
     if total_iterations == 0 {
         msg!("Failed to complete one iteration");
-        return Err(ProgramError::Custom(0));
+        return Err(BalexError::NoEventsConsumed.into());
     }
 
     let aob_params = agnostic_orderbook::instruction::consume_events::Params {
@@ -336,7 +681,7 @@ pub fn consume_order_events(
         market: &ctx.accounts.orderbook,
         event_queue: &ctx.accounts.event_queue,
         authority: &ctx.accounts.system_program.to_account_info(), // No impact with AOB as a lib
-        reward_target: &ctx.accounts.market.to_account_info(),
+        reward_target: &ctx.accounts.reward_target,
     };
 
     if let Err(error) = agnostic_orderbook::instruction::consume_events::process(
@@ -348,19 +693,47 @@ pub fn consume_order_events(
         return Err(error);
     }
 
+    let reward = ctx.accounts.market.load_mut()?.take_crank_reward(total_iterations, queue_len);
+    if reward > 0 {
+        **ctx.accounts.market.to_account_info().try_borrow_mut_lamports()? -= reward;
+        **ctx.accounts.reward_target.try_borrow_mut_lamports()? += reward;
+        msg!("Crank reward {} lamports", reward);
+    }
+
     Ok(())
 }
 
-fn consume_event(
-    accounts_slice: &[AccountInfo],
+fn consume_event<'info>(
+    program_id: &Pubkey,
+    accounts_slice: &[AccountInfo<'info>],
     event: Event,
-    market: &AccountLoader<LexMarket>,
+    market: &AccountLoader<'info, LexMarket>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
 ) -> Result<(), ProgramError> {
     let mut user_accounts: Vec<&AccountInfo> = accounts_slice.iter().collect();
 
     user_accounts.sort_unstable_by_key(|a| a.key());
 
     match event {
+        Event::Fill {
+            taker_side: _,
+            maker_order_id: _,
+            quote_size: _,
+            base_size,
+            maker_callback_info,
+            taker_callback_info,
+        } if maker_callback_info == taker_callback_info => {
+            // Self trade with DecrementTake, user can't lend to itself so both sides are released without a debt
+            let user_account_loader = get_user_account_loader(&user_accounts, &taker_callback_info)?;
+            let mut user_account = load_user_account_mut(&user_account_loader)?;
+
+            msg!("Self trade of {} is released", base_size);
+
+            user_account.base_open_lend -= base_size;
+            user_account.base_free += base_size;
+            user_account.base_open_borrow -= base_size;
+        }
         Event::Fill {
             taker_side,
             maker_order_id,
@@ -369,14 +742,14 @@ fn consume_event(
             maker_callback_info,
             taker_callback_info,
         } => {
-            let taker_account_loader = get_user_account_loader(&user_accounts, &taker_callback_info);
-            let taker_account = &mut taker_account_loader.load_mut()?;
+            let taker_account_loader = get_user_account_loader(&user_accounts, &taker_callback_info)?;
+            let taker_account = &mut load_user_account_mut(&taker_account_loader)?;
 
-            let maker_account_loader = get_user_account_loader(&user_accounts, &maker_callback_info);
-            let maker_account = &mut maker_account_loader.load_mut()?;
+            let maker_account_loader = get_user_account_loader(&user_accounts, &maker_callback_info)?;
+            let maker_account = &mut load_user_account_mut(&maker_account_loader)?;
 
-            let borrower: &mut UserAccount;
-            let lender: &mut UserAccount;
+            let borrower: &mut UserAccountMut;
+            let lender: &mut UserAccountMut;
             match taker_side {
                 Side::Ask => {
                     borrower = maker_account;
@@ -388,9 +761,47 @@ fn consume_event(
                 }
             }
 
+            let market_key = market.key();
             let market_data = &mut market.load_mut()?;
 
-            create_debt(base_size, (maker_order_id >> 64) as u64, lender, borrower, market_data)?;
+            // Checked before creating debt account, failed events don't revert the transaction
+            if !lender.has_debt_room() || !borrower.has_debt_room() {
+                msg!("Max open debts reached.");
+                return Err(BalexError::OpenDebtsLimitReached.into());
+            }
+
+            let debt_id = market_data.next_debt_id;
+            let (debt_key, _) = get_debt_address(&market_key, debt_id, program_id);
+            let debt_account_id = user_accounts.binary_search_by_key(&debt_key, |acc| acc.key())
+                .map_err(|_| {
+                    msg!("Debt account {} of debt {} is not provided", debt_key, debt_id);
+                    BalexError::DebtAccountMissing
+                })?;
+
+            let debt_loader = create_debt_account(
+                program_id,
+                &market_key,
+                debt_id,
+                user_accounts[debt_account_id],
+                payer,
+                system_program,
+            )?;
+
+            {
+                let debt = &mut debt_loader.load_init()?;
+                create_debt(
+                    debt,
+                    market_key,
+                    payer.key(),
+                    base_size,
+                    (maker_order_id >> 64) as u64,
+                    lender,
+                    borrower,
+                    market_data,
+                    Clock::get()?.unix_timestamp,
+                )?;
+            }
+            debt_loader.exit(program_id)?;
         }
         Event::Out {
             side,
@@ -399,14 +810,15 @@ fn consume_event(
             delete,
             callback_info,
         } => {
-            let user_account_loader = get_user_account_loader(&user_accounts, &callback_info);
-            let mut user_account = user_account_loader.load_mut()?;
+            let user_account_loader = get_user_account_loader(&user_accounts, &callback_info)?;
+            let mut user_account = load_user_account_mut(&user_account_loader)?;
 
             msg!("base_size {} delete {}", base_size, delete);
 
             match side {
                 Side::Ask => {
-                    user_account.base_locked -= base_size;
+                    user_account.base_open_lend -= base_size;
+                    user_account.base_free += base_size;
                 },
                 Side::Bid => {
                     user_account.base_open_borrow -= base_size;
@@ -422,15 +834,16 @@ fn consume_event(
     Ok(())
 }
 
-fn get_user_account_loader<'info>(user_accounts: &Vec<&AccountInfo<'info>>, callback_info: &Vec<u8>) -> AccountLoader<'info, UserAccount> {
+fn get_user_account_loader<'info>(user_accounts: &Vec<&AccountInfo<'info>>, callback_info: &Vec<u8>) -> Result<AccountLoader<'info, UserAccount>, ProgramError> {
     let owner = Pubkey::new(&callback_info[..]);
     msg!("owner {}", owner);
     msg!("user accounts: ");
     for user_account in user_accounts {
         msg!("user_account: {}", user_account.key());
     }
-    let user_account_id = user_accounts.binary_search_by_key(&owner, |acc| acc.key()).unwrap();
-    AccountLoader::try_from(&user_accounts[user_account_id]).unwrap()
+    let user_account_id = user_accounts.binary_search_by_key(&owner, |acc| acc.key())
+        .map_err(|_| BalexError::UserAccountMissing)?;
+    AccountLoader::try_from(&user_accounts[user_account_id])
 }
 
 // Used for cranker
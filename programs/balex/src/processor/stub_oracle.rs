@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::error::BalexError;
 use crate::state::{StubPrice};
 
 #[derive(Accounts)]
@@ -6,7 +7,9 @@ pub struct SetStubPrice<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
-    #[account(init_if_needed, payer=admin)]
+    // Freshly created account has no authority yet, it is taken by the signer below
+    #[account(init_if_needed, payer=admin,
+        constraint = stub_price.authority == Pubkey::default() || stub_price.authority == admin.key() @ BalexError::Unauthorized)]
     pub stub_price: Account<'info, StubPrice>,
     
     #[account()]
@@ -14,6 +17,9 @@ pub struct SetStubPrice<'info> {
 }
 
 pub fn set_stub_price(ctx: Context<SetStubPrice>, price: i64, conf: u64) -> ProgramResult {
+    if ctx.accounts.stub_price.authority == Pubkey::default() {
+        ctx.accounts.stub_price.authority = ctx.accounts.admin.key();
+    }
     ctx.accounts.stub_price.price = price;
     ctx.accounts.stub_price.conf = conf;
 
@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_lang::{AnchorDeserialize, AnchorSerialize};
-use anchor_lang::solana_program::clock::Clock;
-use std::time::{SystemTime, UNIX_EPOCH};
-use pyth_client::{load_price};
+use anchor_lang::error::ErrorCode;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize, Discriminator};
+use std::cell::RefMut;
+use std::convert::TryInto;
+use std::ops::{Deref, DerefMut};
+use pyth_client::{load_price, PriceStatus};
+use crate::error::BalexError;
+use crate::events::{DebtCreated, DebtSettled};
 
 pub static CALLBACK_INFO_LEN: u64 = 32;
 pub static CALLBACK_ID_LEN: u64 = 32;
@@ -15,6 +19,31 @@ pub enum OracleType {
     Pyth
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum MarketStatus {
+    Active,
+    ReduceOnly, // No new orders or withdrawals, existing positions can still be reduced
+    Paused, // Only cancels work
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OrderType {
+    Limit, // Matches what it can and rests the remaining on the book
+    PostOnly, // Only rests on the book, rejected if it would match
+    ImmediateOrCancel, // Matches what it can, the remaining is cancelled
+    FillOrKill, // Matches completely or rejected
+}
+
+// What happens when an order would match another order of the same user account
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum SelfTradeBehavior {
+    DecrementTake, // Both orders are decremented by the matched qty without creating a debt
+
+    CancelProvide,
+    AbortTransaction,
+}
+
 // TODO: Complete it as we go
 #[account]
 #[derive(Default)]
@@ -23,37 +52,138 @@ pub struct StubPrice {
   pub price: i64,
   /// confidence interval around the price
   pub conf: u64,
+  /// the only signer allowed to move the price, set on init
+  pub authority: Pubkey,
 }
 
-#[zero_copy]
-#[derive(AnchorDeserialize, AnchorSerialize, Default)]
+// Each debt lives in its own PDA with seeds [market, id]
+#[account(zero_copy)]
+#[derive(Default)]
 pub struct Debt {
+    pub market: Pubkey,
     pub lender: Pubkey,
     pub borrower: Pubkey,
+    pub id: u64,
     pub timestamp: i64, //Used to calculate return interest 
-    pub interest_rate: u64, //fp32
+    pub interest_rate: u64, //Percent per hour
     pub qty: u64, //If zero it means it's empty debt
     pub liquid_qty: u64,
+    pub accrued_interest: u64, //Unpaid interest until timestamp, it doesn't bear interest
+    pub maturity: i64, //Zero means open-ended debt
+    pub payer: Pubkey, //Paid rent of the debt account, gets it back by close_debt once debt is settled
 }
 
+// Interest rate of debts is percent per hour
+pub const INTEREST_RATE_DENOMINATOR: u128 = 60 * 60 * 100;
+
 impl Debt {
-    pub fn get_debt_as_of_now(self: &Self) -> u64 {
-        #[cfg(target_arch = "bpf")]
-        let diff_timestamp = (Clock::get().unwrap().unix_timestamp - self.timestamp) as u64;
+    // Simple interest accrued from debt timestamp until `now`, rounded half up.
+    // `now` is passed explicitly so program and off-chain clients get the same result.
+    pub fn get_debt_as_of_now(self: &Self, now: i64) -> Result<u64, ProgramError> {
+        let interest = self.accrued_since_timestamp(now)?
+            .checked_add(INTEREST_RATE_DENOMINATOR / 2)
+            .ok_or(BalexError::MathOverflow)?
+            / INTEREST_RATE_DENOMINATOR;
+
+        let total = (self.qty as u128)
+            .checked_add(self.accrued_interest as u128)
+            .and_then(|x| x.checked_add(interest))
+            .and_then(|x| x.checked_sub(self.liquid_qty as u128))
+            .ok_or(BalexError::MathOverflow)?;
+
+        if total > u64::MAX as u128 {
+            return Err(BalexError::MathOverflow.into());
+        }
+
+        Ok(total as u64)
+    }
 
-        #[cfg(not(target_arch = "bpf"))]
-        let diff_timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - self.timestamp as u64;
+    // Unpaid interest as of `now`. Interest since timestamp is rounded down here, so settling it
+    // by repay never adds rounding to what borrower owes
+    pub fn get_interest_as_of_now(self: &Self, now: i64) -> Result<u64, ProgramError> {
+        let interest = (self.accrued_interest as u128)
+            .checked_add(self.accrued_since_timestamp(now)? / INTEREST_RATE_DENOMINATOR)
+            .ok_or(BalexError::MathOverflow)?;
 
-        let profit_rate: f64 = (self.interest_rate * diff_timestamp) as f64 / (60.*60.*100.);
-        msg!("{} {} {}", self.qty, diff_timestamp, profit_rate);
+        if interest > u64::MAX as u128 {
+            return Err(BalexError::MathOverflow.into());
+        }
+
+        Ok(interest as u64)
+    }
 
-        (self.qty as f64 * (1. + profit_rate)).round() as u64 - self.liquid_qty
+    fn accrued_since_timestamp(self: &Self, now: i64) -> Result<u128, ProgramError> {
+        let diff_timestamp = now.saturating_sub(self.timestamp).max(0) as u128;
+
+        Ok((self.qty as u128)
+            .checked_mul(self.interest_rate as u128)
+            .and_then(|x| x.checked_mul(diff_timestamp))
+            .ok_or(BalexError::MathOverflow)?)
     }
+
+    // Repays up to `amount` of the debt as of `now` and returns the repaid amount.
+    // Interest is paid first, unpaid interest is kept in accrued_interest so it's never capitalized.
+    pub fn repay(self: &mut Self, amount: u64, now: i64) -> Result<u64, ProgramError> {
+        // Liquidated qty pays principal first, what is more than principal has paid interest
+        let principal = self.qty.saturating_sub(self.liquid_qty);
+        let interest = self.get_interest_as_of_now(now)?.saturating_sub(self.liquid_qty.saturating_sub(self.qty));
+
+        let repaid = amount.min(principal.checked_add(interest).ok_or(BalexError::MathOverflow)?);
+        let interest_paid = repaid.min(interest);
+
+        self.qty = principal - (repaid - interest_paid);
+        self.accrued_interest = interest - interest_paid;
+        self.liquid_qty = 0;
+        self.timestamp = now;
+
+        Ok(repaid)
+    }
+
+    // Debt is liquidatable regardless of borrower health after maturity plus grace period
+    pub fn is_overdue(self: &Self, now: i64, grace_period: u64) -> bool {
+        self.maturity != 0 && now > self.maturity.saturating_add(grace_period as i64)
+    }
+}
+
+pub fn get_debt_address(market: &Pubkey, debt_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&market.to_bytes(), &debt_id.to_le_bytes()], program_id)
 }
 
+pub fn get_user_account_address(market: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&market.to_bytes(), &owner.to_bytes()], program_id)
+}
 
+pub const DEFAULT_OVER_COLLATERAL_PERCENT: u8 = 50;
+pub const DEFAULT_LIQUIDATION_BONUS_PERCENT: u8 = 3;
+pub const DEFAULT_CLOSE_FACTOR_PERCENT: u8 = 50;
+pub const DEFAULT_MIN_ORDER_SIZE: u64 = 1;
+pub const DEFAULT_MAX_INTEREST_RATE: u64 = 100;
+// Upper bound of max_interest_rate, so qty * rate * timestamp aggregates of any u64 qty fit in u128
+pub const MAX_INTEREST_RATE_LIMIT: u64 = 1_000_000;
+pub const DEFAULT_MAX_CONFIDENCE_BPS: u64 = 200;
+pub const DEFAULT_MAX_PRICE_STALENESS_SLOTS: u64 = 25;
+pub const DEFAULT_PROTOCOL_FEE_PERCENT: u8 = 0;
+pub const DEFAULT_CRANK_FEE: u64 = 5000;
+pub const DEFAULT_GRACE_PERIOD: u64 = 24 * 60 * 60;
+pub const DEFAULT_INSURANCE_PERCENT: u8 = 0;
+pub const DEFAULT_LIQUIDATION_AUCTION_DURATION: u64 = 0;
 
-pub const TOTAL_OPEN_DEBTS_SIZE: usize = 256;
+// Risk parameters of a market which admin can change by update_market_params
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MarketParams {
+    pub over_collateral_percent: u8,
+    pub liquidation_bonus_percent: u8,
+    pub close_factor_percent: u8,
+    pub min_order_size: u64,
+    pub max_interest_rate: u64,
+    pub max_confidence_bps: u64,
+    pub max_price_staleness_slots: u64,
+    pub protocol_fee_percent: u8,
+    pub crank_fee: u64,
+    pub grace_period: u64,
+    pub insurance_percent: u8,
+    pub liquidation_auction_duration: u64,
+}
 
 #[account(zero_copy)]
 pub struct LexMarket {
@@ -65,22 +195,190 @@ pub struct LexMarket {
     pub price_oracle: Pubkey,
 
     pub orderbook: Pubkey,
+    // Admin hands over its role and sets other roles, new admin should accept the role
+    // It also winds down a market, retiring its insurance fund once paused and closing it once empty
     pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    // Updates market params and status, fee rates (protocol fee, insurance share and crank fee) are market params too
+    pub risk_admin: Pubkey,
+    // Claims protocol fees, it doesn't set fee rates
+    pub fee_admin: Pubkey,
+    // Can pause the market but not resume it
+    pub guardian: Pubkey,
+
+    pub next_debt_id: u64,
+    pub open_debts_cnt: u64,
 
-    pub debts: [Debt; TOTAL_OPEN_DEBTS_SIZE],
+    pub min_order_size: u64,
+    pub max_interest_rate: u64,
+    // Max ratio of oracle confidence interval to price, in basis points
+    pub max_confidence_bps: u64,
+    // Max number of slots since oracle price was published
+    pub max_price_staleness_slots: u64,
+
+    // Protocol fees in base, fee_balance is kept in base vault until admin claims it
+    pub fee_balance: u64,
+    pub total_fees: u64,
+    pub total_interest_paid: u64,
+    // Base lenders lost to debts written off by resolve_bad_debt
+    pub total_bad_debt: u64,
+    // Base kept in base vault to cover lender losses before they are written off, funded by
+    // insurance share of protocol fees and liquidation bonuses and by deposit_insurance
+    pub insurance_balance: u64,
+    pub total_insurance_paid: u64,
+
+    // Lamports paid by each new order, kept in market account until crankers consume the events
+    pub crank_fee: u64,
+    pub crank_fee_budget: u64,
+
+    // Term of all debts of the market in seconds, zero means open-ended. Set once at market creation
+    // so orders of different terms never match; each term bucket is its own market
+    pub loan_term: u64,
+    // Seconds after maturity that a debt becomes liquidatable regardless of health
+    pub grace_period: u64,
+    // Seconds for liquidation bonus to grow from zero to liquidation_bonus_percent once borrower
+    // becomes unhealthy, zero means flat liquidation bonus
+    pub liquidation_auction_duration: u64,
 
     //Ratio of over collateralization, num between 0-100
     pub over_collateral_percent: u8,
+    // Extra quote given to liquidator on top of oracle price, num between 0-100
+    pub liquidation_bonus_percent: u8,
+    // Max part of a debt which can be liquidated at once, num between 1-100
+    pub close_factor_percent: u8,
     pub signer_bump: u8,
     pub oracle_type: OracleType,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    // Share of paid interest taken as protocol fee, num between 0-100
+    pub protocol_fee_percent: u8,
+    // Share of protocol fees and liquidation bonuses given to insurance fund, num between 0-100
+    pub insurance_percent: u8,
+    pub status: MarketStatus,
+}
+
+impl LexMarket {
+    pub fn set_params(self: &mut Self, params: &MarketParams) {
+        self.over_collateral_percent = params.over_collateral_percent;
+        self.liquidation_bonus_percent = params.liquidation_bonus_percent;
+        self.close_factor_percent = params.close_factor_percent;
+        self.min_order_size = params.min_order_size;
+        self.max_interest_rate = params.max_interest_rate;
+        self.max_confidence_bps = params.max_confidence_bps;
+        self.max_price_staleness_slots = params.max_price_staleness_slots;
+        self.protocol_fee_percent = params.protocol_fee_percent;
+        self.crank_fee = params.crank_fee;
+        self.grace_period = params.grace_period;
+        self.insurance_percent = params.insurance_percent;
+        self.liquidation_auction_duration = params.liquidation_auction_duration;
+    }
+
+    // New orders and withdrawals are only allowed on an active market
+    pub fn is_active(self: &Self) -> bool {
+        self.status == MarketStatus::Active
+    }
+
+    pub fn is_paused(self: &Self) -> bool {
+        self.status == MarketStatus::Paused
+    }
+
+    // Takes protocol fee from interest paid to lender, returns the fee
+    // Insurance share of the fee goes to insurance fund, the rest can be claimed by admin
+    pub fn take_protocol_fee(self: &mut Self, interest: u64) -> Result<u64, ProgramError> {
+        let fee = (interest as u128 * self.protocol_fee_percent as u128 / 100) as u64;
+        let insurance = self.insurance_share(fee);
 
-    _padding: [u8; 5]
+        self.insurance_balance = self.insurance_balance.checked_add(insurance).ok_or(BalexError::MathOverflow)?;
+        self.fee_balance = self.fee_balance.checked_add(fee - insurance).ok_or(BalexError::MathOverflow)?;
+        self.total_fees = self.total_fees.checked_add(fee).ok_or(BalexError::MathOverflow)?;
+        self.total_interest_paid = self.total_interest_paid.checked_add(interest).ok_or(BalexError::MathOverflow)?;
+
+        Ok(fee)
+    }
+
+    pub fn get_maturity(self: &Self, now: i64) -> i64 {
+        if self.loan_term == 0 {
+            return 0;
+        }
+        now.saturating_add(self.loan_term as i64)
+    }
+
+    // Share of crank fee budget for consuming `consumed` of `queue_len` queued events, so budget is drained with the queue
+    pub fn take_crank_reward(self: &mut Self, consumed: u64, queue_len: u64) -> u64 {
+        if queue_len == 0 {
+            return 0;
+        }
+
+        let reward = (self.crank_fee_budget as u128 * consumed.min(queue_len) as u128 / queue_len as u128) as u64;
+        self.crank_fee_budget -= reward;
+
+        reward
+    }
+
+    pub fn insurance_share(self: &Self, amount: u64) -> u64 {
+        (amount as u128 * self.insurance_percent as u128 / 100) as u64
+    }
+
+    // Covers as much of the loss as insurance balance allows, returns the covered part
+    pub fn use_insurance(self: &mut Self, loss: u64) -> Result<u64, ProgramError> {
+        let covered = loss.min(self.insurance_balance);
+        self.insurance_balance -= covered;
+        self.total_insurance_paid = self.total_insurance_paid.checked_add(covered).ok_or(BalexError::MathOverflow)?;
+
+        Ok(covered)
+    }
+
+    // Quote amount given to liquidator for the given quote value, including liquidation bonus
+    pub fn with_liquidation_bonus(self: &Self, quote: u64) -> Result<u64, ProgramError> {
+        self.with_bonus_bps(quote, self.liquidation_bonus_percent as u64 * 100)
+    }
+
+    pub fn with_bonus_bps(self: &Self, quote: u64, bonus_bps: u64) -> Result<u64, ProgramError> {
+        let quote = (quote as u128)
+            .checked_mul(10000 + bonus_bps as u128)
+            .ok_or(BalexError::MathOverflow)?
+            + 5000;
+        (quote / 10000).try_into().map_err(|_| BalexError::MathOverflow.into())
+    }
+
+    // Liquidation bonus in basis points for an auction started at auction_start, it grows linearly
+    // from zero to liquidation_bonus_percent over auction duration
+    pub fn get_liquidation_bonus_bps(self: &Self, auction_start: i64, now: i64) -> u64 {
+        let max_bonus_bps = self.liquidation_bonus_percent as u64 * 100;
+        let duration = self.liquidation_auction_duration;
+
+        if duration == 0 {
+            return max_bonus_bps;
+        }
+        if auction_start == 0 {
+            return 0;
+        }
+
+        let elapsed = now.saturating_sub(auction_start).max(0) as u64;
+        (max_bonus_bps as u128 * elapsed.min(duration) as u128 / duration as u128) as u64
+    }
+
+    // Ceil of close factor part of the debt
+    pub fn max_liquidation_qty(self: &Self, debt_qty: u64) -> Result<u64, ProgramError> {
+        let qty = (debt_qty as u128 * self.close_factor_percent as u128 + 99) / 100;
+        qty.try_into().map_err(|_| BalexError::MathOverflow.into())
+    }
 }
 
 // current assumption is that we only are handling one pair of token (lend usdt with eth)
+// Default capacity of open orders and open debts of a new user account
 pub const USER_OPEN_ORDERS_SIZE: usize = 16;
 pub const USER_OPEN_DEBTS_SIZE: usize = 16;
 
+pub const USER_MAX_OPEN_ORDERS_SIZE: usize = 1024;
+pub const USER_MAX_OPEN_DEBTS_SIZE: usize = 1024;
+
+// Open order slot is order id (u128), expiry timestamp (i64) and client order id (u64)
+pub const OPEN_ORDER_LEN: usize = 32;
+pub const OPEN_DEBT_LEN: usize = 8;
+
+// Open orders and open debts (u64) are stored after UserAccount, in this order,
+// so their capacity can be changed with resize_user_account
 #[account(zero_copy)]
 pub struct UserAccount {
     pub owner: Pubkey,
@@ -93,154 +391,1164 @@ pub struct UserAccount {
 
     pub quote_total: u64, // amount of locked is dynamic per time as price of borrowed collaterals can change
 
-    pub open_orders: [u128; USER_OPEN_ORDERS_SIZE], // TODO: Make length adjustable, also user orderId
-    // debts:
-    pub open_debts: [u16; USER_OPEN_DEBTS_SIZE], // TODO: Make length adjustable
+    // Aggregates of debts borrowed by user so total debt is known without loading every debt account
+    pub borrow_qty: u64, // Sum of qty and accrued_interest
+    pub borrow_liquid_qty: u64, // Sum of liquid_qty
+    pub borrow_rate_sum: u128, // Sum of qty * interest_rate
+    pub borrow_rate_time_sum: u128, // Sum of qty * interest_rate * timestamp
 
-    pub open_orders_cnt: u8,
+    // When health of the user dropped below 100 and its liquidation auction opened, zero if none is open
+    pub liquidation_auction_start: i64,
+
+    pub open_orders_cnt: u16,
+    pub open_debts_cnt: u16,
+
+    pub open_orders_capacity: u16,
+    pub open_debts_capacity: u16,
+}
+
+// Size of user account with the given capacities, including discriminator
+pub fn get_user_account_space(open_orders_capacity: usize, open_debts_capacity: usize) -> usize {
+    8 + std::mem::size_of::<UserAccount>() + open_orders_capacity * OPEN_ORDER_LEN + open_debts_capacity * OPEN_DEBT_LEN
+}
 
+pub const LEGACY_ORDERS_SIZE: usize = 16;
+pub const LEGACY_DEBTS_SIZE: usize = 16;
+
+// Layout of user accounts before open orders and debts moved to the tail, only kept to migrate them
+#[zero_copy]
+pub struct LegacyUserAccount {
+    pub owner: Pubkey,
+    pub base_free: u64,
+    pub base_locked: u64,
+    pub base_open_lend: u64,
+    pub base_open_borrow: u64,
+    pub quote_total: u64,
+    pub open_orders: [u128; LEGACY_ORDERS_SIZE],
+    // Indexes of debts which were kept in the market account
+    pub open_debts: [u16; LEGACY_DEBTS_SIZE],
+    pub open_orders_cnt: u8,
     pub open_debts_cnt: u8,
+    _padding: [u8; 6],
+}
+
+pub const LEGACY_USER_ACCOUNT_LEN: usize = 8 + std::mem::size_of::<LegacyUserAccount>();
 
-    _padding: [u8; 6]
+// Legacy account has the same discriminator, but its length doesn't match capacities read from its header
+pub fn is_legacy_user_account(data: &[u8]) -> bool {
+    if data.len() != LEGACY_USER_ACCOUNT_LEN || data[..8] != UserAccount::discriminator() {
+        return false;
+    }
+
+    let user_account: &UserAccount = bytemuck::from_bytes(&data[8..8 + std::mem::size_of::<UserAccount>()]);
+    get_user_account_space(user_account.open_orders_capacity as usize, user_account.open_debts_capacity as usize) != data.len()
+}
+
+// Rewrites legacy account data, already grown to default capacities, into the header and the tail.
+// Open orders keep their ids without expiry or client order id
+pub fn migrate_legacy_user_account(data: &mut [u8]) -> ProgramResult {
+    let legacy: LegacyUserAccount = *bytemuck::from_bytes(&data[8..LEGACY_USER_ACCOUNT_LEN]);
+
+    // Legacy debts lived in the market account, there is nothing left to point them to
+    if legacy.open_debts_cnt > 0 {
+        msg!("Legacy user account has {} open debts", legacy.open_debts_cnt);
+        return Err(BalexError::LegacyDebtsOpen.into());
+    }
+
+    if data.len() != get_user_account_space(USER_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE) {
+        return Err(BalexError::InvalidUserAccountCapacity.into());
+    }
+
+    data[8..].fill(0);
+    let (head, tail) = data.split_at_mut(8 + std::mem::size_of::<UserAccount>());
+    let user_account: &mut UserAccount = bytemuck::from_bytes_mut(&mut head[8..]);
+
+    user_account.owner = legacy.owner;
+    user_account.base_free = legacy.base_free;
+    user_account.base_locked = legacy.base_locked;
+    user_account.base_open_lend = legacy.base_open_lend;
+    user_account.base_open_borrow = legacy.base_open_borrow;
+    user_account.quote_total = legacy.quote_total;
+    user_account.open_orders_capacity = USER_OPEN_ORDERS_SIZE as u16;
+    user_account.open_debts_capacity = USER_OPEN_DEBTS_SIZE as u16;
+
+    let open_orders = legacy.open_orders;
+    for order_id in open_orders.iter().take(legacy.open_orders_cnt as usize) {
+        user_account.add_order(tail, *order_id, 0, 0)?;
+    }
+
+    Ok(())
 }
 
 impl UserAccount {
-    pub fn remove_order(self: &mut Self, order_id: u128) -> ProgramResult {
-        for i in 0..self.open_orders_cnt as usize {
-            if self.open_orders[i] == order_id {
-                self.open_orders[i] = self.open_orders[self.open_orders_cnt as usize - 1];
-                self.open_orders[self.open_orders_cnt as usize - 1] = 0;
+    // Offset of open debts in the tail
+    pub fn open_debts_offset(self: &Self) -> usize {
+        self.open_orders_capacity as usize * OPEN_ORDER_LEN
+    }
+
+    pub fn get_open_order(self: &Self, tail: &[u8], i: usize) -> u128 {
+        let offset = i * OPEN_ORDER_LEN;
+        u128::from_le_bytes(tail[offset..offset + 16].try_into().unwrap())
+    }
+
+    // Zero means the order is good till cancel
+    pub fn get_open_order_expiry(self: &Self, tail: &[u8], i: usize) -> i64 {
+        let offset = i * OPEN_ORDER_LEN + 16;
+        i64::from_le_bytes(tail[offset..offset + 8].try_into().unwrap())
+    }
+
+    pub fn is_open_order_expired(self: &Self, tail: &[u8], i: usize, now: i64) -> bool {
+        let expiry_timestamp = self.get_open_order_expiry(tail, i);
+        expiry_timestamp != 0 && now >= expiry_timestamp
+    }
+
+    // Zero means the order has no client order id
+    pub fn get_open_order_client_id(self: &Self, tail: &[u8], i: usize) -> u64 {
+        let offset = i * OPEN_ORDER_LEN + 24;
+        u64::from_le_bytes(tail[offset..offset + 8].try_into().unwrap())
+    }
+
+    pub fn find_order_by_client_id(self: &Self, tail: &[u8], client_order_id: u64) -> Option<u128> {
+        (0..self.open_orders_cnt as usize)
+            .find(|&i| self.get_open_order_client_id(tail, i) == client_order_id)
+            .map(|i| self.get_open_order(tail, i))
+    }
+
+    fn set_open_order(self: &Self, tail: &mut [u8], i: usize, order_id: u128, expiry_timestamp: i64, client_order_id: u64) {
+        let offset = i * OPEN_ORDER_LEN;
+        tail[offset..offset + 16].copy_from_slice(&order_id.to_le_bytes());
+        tail[offset + 16..offset + 24].copy_from_slice(&expiry_timestamp.to_le_bytes());
+        tail[offset + 24..offset + 32].copy_from_slice(&client_order_id.to_le_bytes());
+    }
+
+    pub fn get_open_debt(self: &Self, tail: &[u8], i: usize) -> u64 {
+        let offset = self.open_debts_offset() + i * OPEN_DEBT_LEN;
+        u64::from_le_bytes(tail[offset..offset + OPEN_DEBT_LEN].try_into().unwrap())
+    }
+
+    fn set_open_debt(self: &Self, tail: &mut [u8], i: usize, debt_id: u64) {
+        let offset = self.open_debts_offset() + i * OPEN_DEBT_LEN;
+        tail[offset..offset + OPEN_DEBT_LEN].copy_from_slice(&debt_id.to_le_bytes());
+    }
+
+    pub fn has_debt_room(self: &Self) -> bool {
+        self.open_debts_cnt < self.open_debts_capacity
+    }
+
+    pub fn add_order(self: &mut Self, tail: &mut [u8], order_id: u128, expiry_timestamp: i64, client_order_id: u64) -> ProgramResult {
+        if self.open_orders_cnt >= self.open_orders_capacity {
+            msg!("Max open orders reached.");
+            return Err(BalexError::OpenOrdersLimitReached.into());
+        }
+        self.set_open_order(tail, self.open_orders_cnt as usize, order_id, expiry_timestamp, client_order_id);
+        self.open_orders_cnt += 1;
+        Ok(())
+    }
+
+    pub fn remove_order(self: &mut Self, tail: &mut [u8], order_id: u128) -> ProgramResult {
+        let last = self.open_orders_cnt as usize;
+        for i in 0..last {
+            if self.get_open_order(tail, i) == order_id {
+                let last_offset = (last - 1) * OPEN_ORDER_LEN;
+                tail.copy_within(last_offset..last_offset + OPEN_ORDER_LEN, i * OPEN_ORDER_LEN);
+                self.set_open_order(tail, last - 1, 0, 0, 0);
                 self.open_orders_cnt -= 1;
                 return Ok(());
             }
         }
-        return Err(ProgramError::InvalidArgument);
+        return Err(BalexError::OrderNotFound.into());
+    }
+
+    pub fn add_debt(self: &mut Self, tail: &mut [u8], debt_id: u64) -> ProgramResult {
+        if !self.has_debt_room() {
+            msg!("Max open debts reached.");
+            return Err(BalexError::OpenDebtsLimitReached.into());
+        }
+        self.set_open_debt(tail, self.open_debts_cnt as usize, debt_id);
+        self.open_debts_cnt += 1;
+        Ok(())
     }
-    pub fn remove_debt(self: &mut Self, debt_id: u16) -> ProgramResult {
-        for i in 0..self.open_debts_cnt as usize {
-            if self.open_debts[i] == debt_id {
-                self.open_debts[i] = self.open_debts[self.open_debts_cnt as usize - 1];
-                self.open_debts[self.open_debts_cnt as usize - 1] = 0;
+
+    pub fn remove_debt(self: &mut Self, tail: &mut [u8], debt_id: u64) -> ProgramResult {
+        let last = self.open_debts_cnt as usize;
+        for i in 0..last {
+            if self.get_open_debt(tail, i) == debt_id {
+                let last_debt = self.get_open_debt(tail, last - 1);
+                self.set_open_debt(tail, i, last_debt);
+                self.set_open_debt(tail, last - 1, 0);
                 self.open_debts_cnt -= 1;
+                // Nothing is left to be liquidated, so a later auction won't start from a stale one
+                if self.open_debts_cnt == 0 {
+                    self.liquidation_auction_start = 0;
+                }
                 return Ok(());
             }
         }
-        return Err(ProgramError::InvalidArgument);
+        return Err(BalexError::DebtNotFound.into());
+    }
+
+    pub fn add_borrow(self: &mut Self, debt: &Debt) -> ProgramResult {
+        let rate = debt.qty as u128 * debt.interest_rate as u128;
+        let rate_time = rate.checked_mul(debt.timestamp.max(0) as u128).ok_or(BalexError::MathOverflow)?;
+
+        self.borrow_qty = self.borrow_qty
+            .checked_add(debt.qty)
+            .and_then(|x| x.checked_add(debt.accrued_interest))
+            .ok_or(BalexError::MathOverflow)?;
+        self.borrow_liquid_qty = self.borrow_liquid_qty.checked_add(debt.liquid_qty).ok_or(BalexError::MathOverflow)?;
+        self.borrow_rate_sum = self.borrow_rate_sum.checked_add(rate).ok_or(BalexError::MathOverflow)?;
+        self.borrow_rate_time_sum = self.borrow_rate_time_sum.checked_add(rate_time).ok_or(BalexError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn remove_borrow(self: &mut Self, debt: &Debt) -> ProgramResult {
+        let rate = debt.qty as u128 * debt.interest_rate as u128;
+        let rate_time = rate.checked_mul(debt.timestamp.max(0) as u128).ok_or(BalexError::MathOverflow)?;
+
+        self.borrow_qty = self.borrow_qty
+            .checked_sub(debt.qty)
+            .and_then(|x| x.checked_sub(debt.accrued_interest))
+            .ok_or(BalexError::MathOverflow)?;
+        self.borrow_liquid_qty = self.borrow_liquid_qty.checked_sub(debt.liquid_qty).ok_or(BalexError::MathOverflow)?;
+        self.borrow_rate_sum = self.borrow_rate_sum.checked_sub(rate).ok_or(BalexError::MathOverflow)?;
+        self.borrow_rate_time_sum = self.borrow_rate_time_sum.checked_sub(rate_time).ok_or(BalexError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn liquidate_borrow(self: &mut Self, amount: u64) -> ProgramResult {
+        self.borrow_liquid_qty = self.borrow_liquid_qty.checked_add(amount).ok_or(BalexError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+// User account with its open orders and open debts tail, loaded together from one borrow of data
+pub struct UserAccountMut<'a> {
+    account: RefMut<'a, UserAccount>,
+    tail: RefMut<'a, [u8]>,
+}
+
+impl<'a> Deref for UserAccountMut<'a> {
+    type Target = UserAccount;
+
+    fn deref(&self) -> &UserAccount {
+        &self.account
+    }
+}
+
+impl<'a> DerefMut for UserAccountMut<'a> {
+    fn deref_mut(&mut self) -> &mut UserAccount {
+        &mut self.account
+    }
+}
+
+impl<'a> UserAccountMut<'a> {
+    pub fn get_open_order(self: &Self, i: usize) -> u128 {
+        self.account.get_open_order(&self.tail, i)
+    }
+
+    pub fn is_open_order_expired(self: &Self, i: usize, now: i64) -> bool {
+        self.account.is_open_order_expired(&self.tail, i, now)
+    }
+
+    pub fn get_open_debt(self: &Self, i: usize) -> u64 {
+        self.account.get_open_debt(&self.tail, i)
+    }
+
+    pub fn find_order_by_client_id(self: &Self, client_order_id: u64) -> Option<u128> {
+        self.account.find_order_by_client_id(&self.tail, client_order_id)
+    }
+
+    pub fn add_order(self: &mut Self, order_id: u128, expiry_timestamp: i64, client_order_id: u64) -> ProgramResult {
+        self.account.add_order(&mut self.tail, order_id, expiry_timestamp, client_order_id)
+    }
+
+    pub fn remove_order(self: &mut Self, order_id: u128) -> ProgramResult {
+        self.account.remove_order(&mut self.tail, order_id)
+    }
+
+    pub fn add_debt(self: &mut Self, debt_id: u64) -> ProgramResult {
+        self.account.add_debt(&mut self.tail, debt_id)
+    }
+
+    pub fn remove_debt(self: &mut Self, debt_id: u64) -> ProgramResult {
+        self.account.remove_debt(&mut self.tail, debt_id)
+    }
+}
+
+// Same as AccountLoader::load_mut, but also gives the tail after UserAccount
+pub fn load_user_account_mut<'a, 'info>(loader: &'a AccountLoader<'info, UserAccount>) -> Result<UserAccountMut<'a>, ProgramError> {
+    let acc_info: &AccountInfo<'info> = loader.as_ref();
+    if !acc_info.is_writable {
+        return Err(ErrorCode::AccountNotMutable.into());
+    }
+
+    let data = acc_info.try_borrow_mut_data()?;
+    if data.len() < 8 + std::mem::size_of::<UserAccount>() || data[..8] != UserAccount::discriminator() {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+
+    if is_legacy_user_account(&data) {
+        msg!("User account has legacy layout, migrate it first");
+        return Err(BalexError::UserAccountNotMigrated.into());
+    }
+
+    let (account, tail) = RefMut::map_split(data, |data| {
+        let (head, tail) = data.split_at_mut(8 + std::mem::size_of::<UserAccount>());
+        (bytemuck::from_bytes_mut(&mut head[8..]), tail)
+    });
+
+    Ok(UserAccountMut { account, tail })
+}
+
+// Price of one quote token in base tokens, as `price * 10^expo`
+#[derive(Clone, Copy, Debug)]
+pub struct OraclePrice {
+    pub price: u64,
+    pub expo: i32,
+}
+
+impl OraclePrice {
+    // Value of one native quote unit in native base units as (numerator, denominator)
+    fn native_ratio(self: &Self, market: &LexMarket) -> Result<(u128, u128), ProgramError> {
+        let expo = self.expo + market.base_decimals as i32 - market.quote_decimals as i32;
+        let scale = 10u128.checked_pow(expo.unsigned_abs()).ok_or(BalexError::MathOverflow)?;
+
+        if expo >= 0 {
+            let num = (self.price as u128).checked_mul(scale).ok_or(BalexError::MathOverflow)?;
+            Ok((num, 1))
+        } else {
+            Ok((self.price as u128, scale))
+        }
+    }
+
+    // Value of quote amount in base, rounded down
+    pub fn quote_to_base(self: &Self, quote: u64, market: &LexMarket) -> Result<u64, ProgramError> {
+        let (num, den) = self.native_ratio(market)?;
+        let base = (quote as u128).checked_mul(num).ok_or(BalexError::MathOverflow)? / den;
+        Ok(base.min(u64::MAX as u128) as u64)
+    }
+
+    // Quote amount required to cover base amount, rounded up
+    pub fn base_to_quote_ceil(self: &Self, base: u64, market: &LexMarket) -> Result<u64, ProgramError> {
+        let (num, den) = self.native_ratio(market)?;
+        let quote = (base as u128)
+            .checked_mul(den)
+            .and_then(|x| x.checked_add(num - 1))
+            .ok_or(BalexError::MathOverflow)?
+            / num;
+        if quote > u64::MAX as u128 {
+            return Err(BalexError::MathOverflow.into());
+        }
+        Ok(quote as u64)
     }
 }
 
-pub fn get_quote_price(oracle_type: &OracleType, oracle_account: &AccountInfo) -> Option<i64> {
-    match oracle_type {
+// Returns quote price of the market oracle, fails if price can't be trusted
+pub fn get_quote_price(market: &LexMarket, oracle_account: &AccountInfo, current_slot: u64) -> Result<OraclePrice, ProgramError> {
+    let price = match market.oracle_type {
         OracleType::Pyth => {
-            let price_data = oracle_account.try_borrow_data().unwrap();
-            let price = load_price(*price_data).unwrap();
-            // match price.agg.status {
-            //    PriceStatus::Trading => Some(price.agg.price),
-            //    _ => Some(price.agg.price) // Change to a safe behavior
-            // }
-            Some(price.agg.price / (10 as i64).pow(price.expo as u32))
+            let price_data = oracle_account.try_borrow_data()?;
+            let price = load_price(*price_data)?;
+
+            match price.agg.status {
+                PriceStatus::Trading => {},
+                _ => {
+                    msg!("Oracle price is not trading");
+                    return Err(BalexError::OraclePriceNotTrading.into());
+                }
+            }
+
+            if current_slot.saturating_sub(price.agg.pub_slot) > market.max_price_staleness_slots {
+                msg!("Oracle price is published at slot {}, current slot is {}", price.agg.pub_slot, current_slot);
+                return Err(BalexError::OraclePriceStale.into());
+            }
+
+            if price.agg.price <= 0 {
+                return Err(BalexError::OracleUnavailable.into());
+            }
+
+            if price.agg.conf as u128 * 10000 > price.agg.price as u128 * market.max_confidence_bps as u128 {
+                msg!("Oracle confidence {} is too wide for price {}", price.agg.conf, price.agg.price);
+                return Err(BalexError::OracleConfidenceTooWide.into());
+            }
+
+            (price.agg.price, price.expo)
         },
         OracleType::Stub => {
-            let price: Account<StubPrice> = Account::try_from(&oracle_account).unwrap();
-            Some(price.price)
+            let price: Account<StubPrice> = Account::try_from(&oracle_account)?;
+            (price.price, 0)
         }
+    };
+
+    if price.0 <= 0 {
+        return Err(BalexError::OracleUnavailable.into());
     }
+
+    Ok(OraclePrice { price: price.0 as u64, expo: price.1 })
 }
 
-pub fn get_user_total_debt(user_account: &UserAccount, market: &LexMarket) -> u64 {
-    let mut total_debt: u64 = 0;
+// Same as sum of get_debt_as_of_now of borrowed debts, but rounding is done once on the total
+pub fn get_user_total_debt(user_account: &UserAccount, now: i64) -> Result<u64, ProgramError> {
+    let accrued = user_account.borrow_rate_sum
+        .checked_mul(now.max(0) as u128)
+        .ok_or(BalexError::MathOverflow)?
+        .saturating_sub(user_account.borrow_rate_time_sum);
 
-    for i in 0..user_account.open_debts_cnt as usize {
-        let debt_id = user_account.open_debts[i] as usize;
-        let debt: &Debt = &market.debts[debt_id];
+    let interest = accrued.checked_add(INTEREST_RATE_DENOMINATOR / 2).ok_or(BalexError::MathOverflow)? / INTEREST_RATE_DENOMINATOR;
 
-        if debt.borrower == user_account.owner {
-            total_debt += debt.get_debt_as_of_now();
-        }
+    let total = (user_account.borrow_qty as u128 + interest).saturating_sub(user_account.borrow_liquid_qty as u128);
+    if total > u64::MAX as u128 {
+        return Err(BalexError::MathOverflow.into());
     }
 
-    total_debt
+    Ok(total as u64)
 }
 
-pub fn get_max_borrow_qty(user_account: &UserAccount, market: &LexMarket, oracle_account: &AccountInfo) -> u64 {
-    let price: u64 = get_quote_price(&market.oracle_type, &oracle_account).unwrap() as u64; // If price is not present?
+pub fn get_max_borrow_qty(user_account: &UserAccount, market: &LexMarket, price: OraclePrice, now: i64) -> Result<u64, ProgramError> {
+    let collateral = price.quote_to_base(user_account.quote_total, market)? as u128;
+    let total_possible = (collateral * 100 / (100 + market.over_collateral_percent as u128)) as u64;
+    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, now)?;
 
-    let total_possible = user_account.quote_total * 100 * price / (100 + market.over_collateral_percent as u64);
-    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, market);
+    Ok(total_possible.saturating_sub(user_total_open_debt))
+}
+
+pub fn get_max_withdraw_qty(user_account: &UserAccount, market: &LexMarket, price: OraclePrice, now: i64) -> Result<u64, ProgramError> {
+    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, now)?;
+
+    let required_base = (user_total_open_debt as u128 * (100 + market.over_collateral_percent as u128) + 99) / 100;
+    if required_base > u64::MAX as u128 {
+        return Err(BalexError::MathOverflow.into());
+    }
+    let safe_backed = price.base_to_quote_ceil(required_base as u64, market)?;
 
-    total_possible.saturating_sub(user_total_open_debt)
+    Ok(user_account.quote_total.saturating_sub(safe_backed))
 }
 
-pub fn get_max_withdraw_qty(user_account: &UserAccount, market: &LexMarket, oracle_account: &AccountInfo) -> u64 {
-    let price: u64 = get_quote_price(&market.oracle_type, &oracle_account).unwrap() as u64; // If price is not present?
+fn health_factor(collateral: u64, debt: u64, market: &LexMarket) -> u64 {
+    if debt == 0 {
+        return 100;
+    }
 
-    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, market);
+    let health = 10000 * collateral as u128 / (debt as u128 * (100 + (market.over_collateral_percent as u128 + 1) / 2));
+    health.min(u64::MAX as u128) as u64
+}
 
-    let safe_backed = (user_total_open_debt * (100 + market.over_collateral_percent as u64) + price * 100 -1) / (price * 100);
+// Returns health factor as percent
+pub fn get_user_health_factor(user_account: &UserAccount, market: &LexMarket, price: OraclePrice, now: i64) -> Result<u64, ProgramError> {
+    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, now)?;
+    let collateral = price.quote_to_base(user_account.quote_total, market)?;
 
-    user_account.quote_total.saturating_sub(safe_backed)
+    Ok(health_factor(collateral, user_total_open_debt, market))
 }
 
+// Health factor of the user once liquid_amount of its debt is liquidated with the given bonus,
+// bonus_bps should be the one liquidate_debts pays for the user
+pub fn get_user_health_factor_after_liquid(liquid_amount: u64, bonus_bps: u64, user_account: &UserAccount, market: &LexMarket, price: OraclePrice, now: i64) -> Result<u64, ProgramError> {
+    let user_total_open_debt = (user_account.base_open_borrow + get_user_total_debt(user_account, now)?)
+        .checked_sub(liquid_amount)
+        .ok_or(BalexError::MathOverflow)?;
 
-// Returns health factor as percent, not accurate and not safe for overflows! TODO: make it fp32
-pub fn get_user_health_factor(user_account: &UserAccount, market: &LexMarket, oracle_account: &AccountInfo) -> u64 {
-    let price: u64 = get_quote_price(&market.oracle_type, &oracle_account).unwrap() as u64; // If price is not present?
+    let liquid_quote = price.base_to_quote_ceil(liquid_amount, market)?;
+    let liquid_quote = market.with_bonus_bps(liquid_quote, bonus_bps)?;
 
-    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, market);
+    let collateral = price.quote_to_base(user_account.quote_total.saturating_sub(liquid_quote), market)?;
 
-    if user_total_open_debt == 0 {
-        return 100;
+    Ok(health_factor(collateral, user_total_open_debt, market))
+}
+
+// Opens liquidation auction of the user when it becomes unhealthy and closes it when it's healthy again,
+// returns whether the auction state changed
+pub fn refresh_liquidation_auction(user_account: &mut UserAccount, health: u64, now: i64) -> bool {
+    if health < 100 && user_account.liquidation_auction_start == 0 {
+        user_account.liquidation_auction_start = now;
+        return true;
+    }
+    if health >= 100 && user_account.liquidation_auction_start != 0 {
+        user_account.liquidation_auction_start = 0;
+        return true;
     }
+    false
+}
 
-    let user_quote = user_account.quote_total;
+// Base value of all collateral of the user for a liquidator, with liquidation bonus taken off
+pub fn get_collateral_base_value(user_account: &UserAccount, market: &LexMarket, price: OraclePrice) -> Result<u64, ProgramError> {
+    let base = price.quote_to_base(user_account.quote_total, market)? as u128;
+    Ok((base * 100 / (100 + market.liquidation_bonus_percent as u128)) as u64)
+}
+
+// Base a liquidator pays for collateral of the user towards debt_left and the quote it seizes for it,
+// collateral is seized pro-rata when it's worth more than debt_left
+pub fn get_collateral_seizure(user_account: &UserAccount, market: &LexMarket, price: OraclePrice, debt_left: u64) -> Result<(u64, u64), ProgramError> {
+    let collateral_value = get_collateral_base_value(user_account, market, price)?;
+    if collateral_value <= debt_left {
+        return Ok((collateral_value, user_account.quote_total));
+    }
 
-    10000 * price * user_quote / (user_total_open_debt * (100 + (market.over_collateral_percent+1) as u64 /2))
+    let seized_quote = (user_account.quote_total as u128 * debt_left as u128 / collateral_value as u128) as u64;
+    Ok((debt_left, seized_quote))
 }
 
-pub fn get_user_health_factor_after_liquid(liquid_amount: u64, user_account: &UserAccount, market: &LexMarket, oracle_account: &AccountInfo) -> u64 {
-    let price: u64 = get_quote_price(&market.oracle_type, &oracle_account).unwrap() as u64; // If price is not present?
+// Base free and all collateral of the user can't pay its debts, so it can only be resolved by resolve_bad_debt
+pub fn is_user_insolvent(user_account: &UserAccount, market: &LexMarket, price: OraclePrice, now: i64) -> Result<bool, ProgramError> {
+    let total_debt = get_user_total_debt(user_account, now)?;
+    let recoverable = user_account.base_free.saturating_add(get_collateral_base_value(user_account, market, price)?);
+    Ok(recoverable < total_debt)
+}
 
-    let user_total_open_debt = user_account.base_open_borrow + get_user_total_debt(user_account, market) - liquid_amount;
+pub fn create_debt(debt: &mut Debt, market_key: Pubkey, payer: Pubkey, qty: u64, interest_rate: u64, lender: &mut UserAccountMut, borrower: &mut UserAccountMut, market: &mut LexMarket, now: i64) -> ProgramResult {
+    if !lender.has_debt_room() || !borrower.has_debt_room() {
+        msg!("Max open debts reached.");
+        return Err(BalexError::OpenDebtsLimitReached.into());
+    }
 
-    if user_total_open_debt == 0 {
-        return 100;
+    let debt_id = market.next_debt_id;
+
+    *debt = Debt {
+        market: market_key,
+        lender: lender.owner,
+        borrower: borrower.owner,
+        id: debt_id,
+        timestamp: now,
+        interest_rate,
+        qty,
+        liquid_qty: 0,
+        accrued_interest: 0,
+        maturity: market.get_maturity(now),
+        payer,
+    };
+
+    market.next_debt_id += 1;
+    market.open_debts_cnt += 1;
+
+    lender.add_debt(debt_id)?;
+    borrower.add_debt(debt_id)?;
+
+    borrower.add_borrow(debt)?;
+    borrower.base_free += qty;
+    borrower.base_open_borrow -= qty;
+
+    lender.base_locked += qty;
+    lender.base_open_lend -= qty;
+
+    msg!("Successfully created debt {} of {} with interest {} maturity {} between {} and {}", debt_id, qty, interest_rate, debt.maturity, lender.owner, borrower.owner);
+
+    emit!(DebtCreated {
+        market: market_key,
+        debt_id,
+        lender: debt.lender,
+        borrower: debt.borrower,
+        qty,
+        interest_rate,
+        maturity: debt.maturity,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+// Pays up to `amount` of the debt to the lender and rebases the debt to now, returns repaid amount and protocol fee.
+// Removing a settled debt (qty is zero) from open debts of lender and borrower is up to the caller
+pub fn pay_debt(debt: &mut Debt, amount: u64, lender: &mut UserAccount, borrower: &mut UserAccount, market: &mut LexMarket, now: i64) -> Result<(u64, u64), ProgramError> {
+    let lent_qty = debt.qty.saturating_sub(debt.liquid_qty);
+    // Repayment pays accrued interest first, unpaid interest is kept apart from the principal
+    let interest = debt.get_interest_as_of_now(now)?;
+
+    borrower.remove_borrow(debt)?;
+    let repaid = debt.repay(amount, now)?;
+    let fee = market.take_protocol_fee(repaid.min(interest))?;
+
+    lender.base_locked = lender.base_locked - lent_qty + debt.qty;
+    lender.base_free += repaid - fee;
+
+    if debt.qty > 0 {
+        borrower.add_borrow(debt)?;
     }
 
-    let liquid_quote = (liquid_amount + price - 1) / price;
-    let liquid_quote = (liquid_quote as f64 * 1.03).round() as u64;
+    Ok((repaid, fee))
+}
 
-    let user_quote = user_account.quote_total - liquid_quote;
-    10000 * price * user_quote / (user_total_open_debt * (100 + (market.over_collateral_percent+1) as u64 /2))
+// Repays the debt to the lender, paying base from borrower is up to the caller
+pub fn repay_debt(debt: &mut Debt, amount: u64, lender: &mut UserAccountMut, borrower: &mut UserAccountMut, market: &mut LexMarket, now: i64) -> Result<u64, ProgramError> {
+    if debt.qty == 0 {
+        return Err(BalexError::DebtSettled.into());
+    }
+
+    if amount == 0 {
+        return Err(BalexError::InvalidRepayAmount.into());
+    }
+
+    let (repaid, fee) = pay_debt(debt, amount, lender, borrower, market, now)?;
+
+    if debt.qty == 0 {
+        market.open_debts_cnt -= 1;
+        borrower.remove_debt(debt.id)?;
+        lender.remove_debt(debt.id)?;
+        msg!("Debt {} is settled with {}", debt.id, repaid);
+    } else {
+        msg!("Repaid {} of debt {}, remaining {} with unpaid interest {}", repaid, debt.id, debt.qty, debt.accrued_interest);
+    }
+
+    emit!(DebtSettled {
+        market: debt.market,
+        debt_id: debt.id,
+        lender: debt.lender,
+        borrower: debt.borrower,
+        amount: repaid,
+        protocol_fee: fee,
+        remaining_qty: debt.qty + debt.accrued_interest,
+        interest_rate: debt.interest_rate,
+        timestamp: now,
+    });
+
+    Ok(repaid)
 }
 
-pub fn create_debt(qty: u64, interest_rate: u64, lender: &mut UserAccount, borrower: &mut UserAccount, market: &mut LexMarket) -> ProgramResult {
-    for i in 0..TOTAL_OPEN_DEBTS_SIZE {
-        if market.debts[i].qty == 0 {
-            market.debts[i] = Debt {
-                lender: lender.owner,
-                borrower: borrower.owner,
-                timestamp: Clock::get().unwrap().unix_timestamp,
-                interest_rate,
-                qty,
-                liquid_qty: 0,
-            };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debt(qty: u64, interest_rate: u64, liquid_qty: u64) -> Debt {
+        Debt {
+            timestamp: 1_000,
+            interest_rate,
+            qty,
+            liquid_qty,
+            ..Debt::default()
+        }
+    }
+
+    #[test]
+    fn no_interest_at_start() {
+        assert_eq!(debt(1000, 4, 0).get_debt_as_of_now(1_000).unwrap(), 1000);
+    }
+
+    #[test]
+    fn no_interest_before_start() {
+        assert_eq!(debt(1000, 4, 0).get_debt_as_of_now(0).unwrap(), 1000);
+    }
+
+    #[test]
+    fn one_hour_interest() {
+        // 4% per hour
+        assert_eq!(debt(1000, 4, 0).get_debt_as_of_now(1_000 + 3600).unwrap(), 1040);
+    }
+
+    #[test]
+    fn interest_is_linear_in_time() {
+        let d = debt(1_000_000, 3, 0);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 1800).unwrap(), 1_015_000);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 3600).unwrap(), 1_030_000);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 10 * 3600).unwrap(), 1_300_000);
+    }
+
+    #[test]
+    fn interest_rounds_half_up() {
+        // 100 * 1 * 1800 / 360000 = 0.5
+        assert_eq!(debt(100, 1, 0).get_debt_as_of_now(1_000 + 1800).unwrap(), 101);
+        // 100 * 1 * 1799 / 360000 < 0.5
+        assert_eq!(debt(100, 1, 0).get_debt_as_of_now(1_000 + 1799).unwrap(), 100);
+    }
+
+    #[test]
+    fn liquid_qty_is_subtracted() {
+        assert_eq!(debt(1000, 4, 300).get_debt_as_of_now(1_000 + 3600).unwrap(), 740);
+    }
+
+    #[test]
+    fn large_values_do_not_overflow() {
+        let d = debt(u64::MAX / 2, 1, 0);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 3600).unwrap(), (u64::MAX / 2) + (u64::MAX / 2) / 100);
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        assert!(debt(u64::MAX, u64::MAX, 0).get_debt_as_of_now(i64::MAX).is_err());
+        assert!(debt(u64::MAX, 100, 0).get_debt_as_of_now(1_000 + 3600).is_err());
+    }
+
+    #[test]
+    fn partial_repay_keeps_accrued_interest() {
+        let mut d = debt(1000, 4, 0);
+        assert_eq!(d.repay(40, 1_000 + 3600).unwrap(), 40);
+        assert_eq!({ d.qty }, 1000);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 3600).unwrap(), 1000);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 2 * 3600).unwrap(), 1040);
+    }
 
-            lender.open_debts[lender.open_debts_cnt as usize] = i as u16;
-            lender.open_debts_cnt += 1;
+    #[test]
+    fn partial_repay_after_liquidation() {
+        let mut d = debt(1000, 4, 100);
+        assert_eq!(d.repay(500, 1_000 + 3600).unwrap(), 500);
+        assert_eq!({ d.qty }, 440);
+        assert_eq!({ d.liquid_qty }, 0);
+    }
 
-            borrower.open_debts[borrower.open_debts_cnt as usize] = i as u16;
-            borrower.open_debts_cnt += 1;
+    #[test]
+    fn partial_repay_less_than_interest_is_not_capitalized() {
+        let mut d = debt(1000, 4, 0);
+        // 40 interest after an hour and only 10 of it is paid
+        assert_eq!(d.repay(10, 1_000 + 3600).unwrap(), 10);
+        assert_eq!({ d.qty }, 1000);
+        assert_eq!({ d.accrued_interest }, 30);
+        assert_eq!(d.get_debt_as_of_now(1_000 + 3600).unwrap(), 1030);
 
-            borrower.base_free += qty;
-            borrower.base_open_borrow -= qty;
+        // Next hour accrues on principal only
+        assert_eq!(d.get_debt_as_of_now(1_000 + 2 * 3600).unwrap(), 1070);
+        assert_eq!(d.repay(50, 1_000 + 2 * 3600).unwrap(), 50);
+        assert_eq!({ d.qty }, 980);
+        assert_eq!({ d.accrued_interest }, 0);
+    }
 
-            lender.base_locked += qty;
-            lender.base_open_lend -= qty;
+    #[test]
+    fn repeated_small_repays_never_cost_more() {
+        let mut repaid_often = debt(1_000_000, 4, 0);
+        let repaid_once = repaid_often;
 
-            msg!("Successfully created a debt of {} with interest {} between {} and {}", qty, interest_rate, lender.owner, borrower.owner);
-            return Ok(())
+        for i in 1..=100 {
+            assert_eq!(repaid_often.repay(1, 1_000 + i * 36).unwrap(), 1);
         }
+
+        let now = 1_000 + 3600;
+        assert!(100 + repaid_often.get_debt_as_of_now(now).unwrap() <= repaid_once.get_debt_as_of_now(now).unwrap());
+    }
+
+    #[test]
+    fn repay_more_than_debt_settles_it() {
+        let mut d = debt(1000, 4, 0);
+        assert_eq!(d.repay(u64::MAX, 1_000 + 3600).unwrap(), 1040);
+        assert_eq!({ d.qty }, 0);
+    }
+
+    #[test]
+    fn open_ended_debt_is_never_overdue() {
+        let d = debt(1000, 1, 0);
+        assert!(!d.is_overdue(i64::MAX, 0));
+    }
+
+    #[test]
+    fn debt_is_overdue_after_grace_period() {
+        let mut d = debt(1000, 1, 0);
+        d.maturity = 5_000;
+        assert!(!d.is_overdue(5_000, 0));
+        assert!(d.is_overdue(5_001, 0));
+        assert!(!d.is_overdue(5_100, 100));
+        assert!(d.is_overdue(5_101, 100));
     }
-    msg!("Debts are full!");
-    Err(ProgramError::Custom(0))
-}
\ No newline at end of file
+
+    #[test]
+    fn user_total_debt_matches_debts() {
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        let debts = [
+            Debt { timestamp: 1_000, interest_rate: 4, qty: 1_000_000, accrued_interest: 700, ..Debt::default() },
+            Debt { timestamp: 5_000, interest_rate: 1, qty: 300_000, ..Debt::default() },
+            Debt { timestamp: 9_000, interest_rate: 7, qty: 25_000, liquid_qty: 1_000, ..Debt::default() },
+        ];
+
+        for d in debts.iter() {
+            user.add_borrow(d).unwrap();
+        }
+
+        let now = 20_000;
+        let expected: u64 = debts.iter().map(|d| d.get_debt_as_of_now(now).unwrap()).sum();
+        assert_eq!(get_user_total_debt(&user, now).unwrap(), expected);
+
+        user.remove_borrow(&debts[1]).unwrap();
+        let expected = debts[0].get_debt_as_of_now(now).unwrap() + debts[2].get_debt_as_of_now(now).unwrap();
+        assert_eq!(get_user_total_debt(&user, now).unwrap(), expected);
+
+        user.liquidate_borrow(500).unwrap();
+        assert_eq!(get_user_total_debt(&user, now).unwrap(), expected - 500);
+    }
+
+    #[test]
+    fn liquidation_payments_rebase_and_settle_debt() {
+        let mut market = market(0, 0);
+        market.protocol_fee_percent = 10;
+        let mut lender: UserAccount = unsafe { std::mem::zeroed() };
+        let mut borrower: UserAccount = unsafe { std::mem::zeroed() };
+        let mut d = debt(1000, 4, 0);
+        lender.base_locked = 1000;
+        borrower.add_borrow(&d).unwrap();
+
+        // 40 interest after an hour is paid first, then 460 of principal
+        assert_eq!(pay_debt(&mut d, 500, &mut lender, &mut borrower, &mut market, 1_000 + 3600).unwrap(), (500, 4));
+        assert_eq!({ d.qty }, 540);
+        assert_eq!({ d.liquid_qty }, 0);
+        assert_eq!({ d.timestamp }, 1_000 + 3600);
+        assert_eq!({ lender.base_locked }, 540);
+        assert_eq!({ lender.base_free }, 496);
+
+        // Interest only accrues on what is left
+        let now = 1_000 + 2 * 3600;
+        assert_eq!(d.get_debt_as_of_now(now).unwrap(), 562);
+        assert_eq!(get_user_total_debt(&borrower, now).unwrap(), 562);
+
+        assert_eq!(pay_debt(&mut d, 562, &mut lender, &mut borrower, &mut market, now).unwrap(), (561, 2));
+        assert_eq!({ d.qty }, 0);
+        assert_eq!({ d.accrued_interest }, 0);
+        assert_eq!({ lender.base_locked }, 0);
+        assert_eq!({ borrower.borrow_qty }, 0);
+        assert_eq!({ borrower.borrow_rate_sum }, 0);
+        assert_eq!(get_user_total_debt(&borrower, now + 3600).unwrap(), 0);
+    }
+
+    fn user_with_capacity(orders: usize, debts: usize) -> (UserAccount, Vec<u8>) {
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        user.open_orders_capacity = orders as u16;
+        user.open_debts_capacity = debts as u16;
+        let tail_len = get_user_account_space(orders, debts) - 8 - std::mem::size_of::<UserAccount>();
+        (user, vec![0; tail_len])
+    }
+
+    #[test]
+    fn orders_and_debts_are_kept_separately_in_tail() {
+        let (mut user, mut tail) = user_with_capacity(2, 3);
+
+        user.add_order(&mut tail, u128::MAX - 1, 0, 0).unwrap();
+        user.add_debt(&mut tail, 7).unwrap();
+        user.add_order(&mut tail, 5, i64::MAX, u64::MAX).unwrap();
+        user.add_debt(&mut tail, 8).unwrap();
+        user.add_debt(&mut tail, 9).unwrap();
+
+        assert_eq!(user.get_open_order(&tail, 0), u128::MAX - 1);
+        assert_eq!(user.get_open_order(&tail, 1), 5);
+        assert_eq!(user.get_open_order_expiry(&tail, 1), i64::MAX);
+        assert_eq!(user.get_open_order_client_id(&tail, 1), u64::MAX);
+        assert_eq!(user.get_open_debt(&tail, 0), 7);
+        assert_eq!(user.get_open_debt(&tail, 2), 9);
+
+        assert!(user.add_order(&mut tail, 6, 0, 0).is_err());
+        assert!(user.add_debt(&mut tail, 10).is_err());
+    }
+
+    fn legacy_user_account_data(open_orders: &[u128], open_debts_cnt: u8) -> Vec<u8> {
+        let mut data = vec![0; LEGACY_USER_ACCOUNT_LEN];
+        data[..8].copy_from_slice(&UserAccount::discriminator());
+
+        let legacy: &mut LegacyUserAccount = bytemuck::from_bytes_mut(&mut data[8..]);
+        legacy.owner = Pubkey::new_unique();
+        legacy.base_free = 100;
+        legacy.base_locked = 20;
+        legacy.base_open_lend = 30;
+        legacy.base_open_borrow = 40;
+        legacy.quote_total = 50;
+        let mut legacy_orders = [0u128; LEGACY_ORDERS_SIZE];
+        legacy_orders[..open_orders.len()].copy_from_slice(open_orders);
+        legacy.open_orders = legacy_orders;
+        legacy.open_orders_cnt = open_orders.len() as u8;
+        legacy.open_debts_cnt = open_debts_cnt;
+        data
+    }
+
+    #[test]
+    fn legacy_user_account_is_migrated_into_tail() {
+        let mut data = legacy_user_account_data(&[7, u128::MAX], 0);
+        let owner = bytemuck::from_bytes::<LegacyUserAccount>(&data[8..]).owner;
+        assert_eq!(LEGACY_USER_ACCOUNT_LEN, 8 + 368);
+        assert!(is_legacy_user_account(&data));
+
+        data.resize(get_user_account_space(USER_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE), 0);
+        migrate_legacy_user_account(&mut data).unwrap();
+        assert!(!is_legacy_user_account(&data));
+
+        let (head, tail) = data.split_at(8 + std::mem::size_of::<UserAccount>());
+        let user: &UserAccount = bytemuck::from_bytes(&head[8..]);
+        assert_eq!(user.owner, owner);
+        assert_eq!({ user.base_free }, 100);
+        assert_eq!({ user.base_locked }, 20);
+        assert_eq!({ user.base_open_lend }, 30);
+        assert_eq!({ user.base_open_borrow }, 40);
+        assert_eq!({ user.quote_total }, 50);
+        assert_eq!({ user.borrow_qty }, 0);
+        assert_eq!({ user.open_orders_cnt }, 2);
+        assert_eq!({ user.open_debts_cnt }, 0);
+        assert_eq!({ user.open_orders_capacity }, USER_OPEN_ORDERS_SIZE as u16);
+        assert_eq!({ user.open_debts_capacity }, USER_OPEN_DEBTS_SIZE as u16);
+        assert_eq!(user.get_open_order(tail, 0), 7);
+        assert_eq!(user.get_open_order(tail, 1), u128::MAX);
+        assert_eq!(user.get_open_order_expiry(tail, 1), 0);
+    }
+
+    #[test]
+    fn legacy_user_account_with_debts_is_not_migrated() {
+        let mut data = legacy_user_account_data(&[7], 1);
+        data.resize(get_user_account_space(USER_OPEN_ORDERS_SIZE, USER_OPEN_DEBTS_SIZE), 0);
+        assert!(migrate_legacy_user_account(&mut data).is_err());
+    }
+
+    #[test]
+    fn removing_moves_last_item_into_place() {
+        let (mut user, mut tail) = user_with_capacity(4, 4);
+        for debt_id in 1..=3 {
+            user.add_debt(&mut tail, debt_id).unwrap();
+        }
+
+        user.remove_debt(&mut tail, 1).unwrap();
+        assert_eq!({ user.open_debts_cnt }, 2);
+        assert_eq!(user.get_open_debt(&tail, 0), 3);
+        assert_eq!(user.get_open_debt(&tail, 1), 2);
+        assert!(user.remove_debt(&mut tail, 1).is_err());
+
+        user.add_order(&mut tail, 11, 0, 0).unwrap();
+        user.remove_order(&mut tail, 11).unwrap();
+        assert_eq!({ user.open_orders_cnt }, 0);
+        assert!(user.remove_order(&mut tail, 11).is_err());
+    }
+
+    #[test]
+    fn order_expiry_and_client_id_move_with_their_order() {
+        let (mut user, mut tail) = user_with_capacity(4, 1);
+        user.add_order(&mut tail, 1, 100, 10).unwrap();
+        user.add_order(&mut tail, 2, 0, 20).unwrap();
+        user.add_order(&mut tail, 3, 300, 30).unwrap();
+
+        user.remove_order(&mut tail, 1).unwrap();
+        assert_eq!(user.get_open_order(&tail, 0), 3);
+        assert_eq!(user.get_open_order_expiry(&tail, 0), 300);
+        assert_eq!(user.get_open_order_client_id(&tail, 0), 30);
+        assert_eq!(user.get_open_order_expiry(&tail, 2), 0);
+        assert_eq!(user.get_open_order_client_id(&tail, 2), 0);
+
+        assert_eq!(user.find_order_by_client_id(&tail, 30), Some(3));
+        assert_eq!(user.find_order_by_client_id(&tail, 20), Some(2));
+        assert_eq!(user.find_order_by_client_id(&tail, 10), None);
+
+        assert!(!user.is_open_order_expired(&tail, 0, 299));
+        assert!(user.is_open_order_expired(&tail, 0, 300));
+        assert!(!user.is_open_order_expired(&tail, 1, i64::MAX));
+    }
+
+    fn market(base_decimals: u8, quote_decimals: u8) -> LexMarket {
+        let mut market: LexMarket = unsafe { std::mem::zeroed() };
+        market.base_decimals = base_decimals;
+        market.quote_decimals = quote_decimals;
+        market
+    }
+
+    #[test]
+    fn protocol_fee_is_share_of_interest_rounded_down() {
+        let mut market = market(0, 0);
+        market.protocol_fee_percent = 15;
+
+        assert_eq!(market.take_protocol_fee(99).unwrap(), 14);
+        assert_eq!(market.take_protocol_fee(0).unwrap(), 0);
+        assert_eq!(market.take_protocol_fee(1000).unwrap(), 150);
+
+        assert_eq!({ market.fee_balance }, 164);
+        assert_eq!({ market.total_fees }, 164);
+        assert_eq!({ market.total_interest_paid }, 1099);
+    }
+
+    #[test]
+    fn insurance_gets_its_share_of_protocol_fee() {
+        let mut market = market(0, 0);
+        market.protocol_fee_percent = 10;
+        market.insurance_percent = 25;
+
+        assert_eq!(market.take_protocol_fee(1000).unwrap(), 100);
+        assert_eq!(market.take_protocol_fee(99).unwrap(), 9);
+
+        assert_eq!({ market.insurance_balance }, 27);
+        assert_eq!({ market.fee_balance }, 82);
+        assert_eq!({ market.total_fees }, 109);
+    }
+
+    #[test]
+    fn insurance_covers_loss_up_to_its_balance() {
+        let mut market = market(0, 0);
+        market.insurance_balance = 100;
+
+        assert_eq!(market.use_insurance(30).unwrap(), 30);
+        assert_eq!(market.use_insurance(100).unwrap(), 70);
+        assert_eq!(market.use_insurance(5).unwrap(), 0);
+
+        assert_eq!({ market.insurance_balance }, 0);
+        assert_eq!({ market.total_insurance_paid }, 100);
+    }
+
+    #[test]
+    fn max_liquidation_qty_is_ceil_of_close_factor_without_overflow() {
+        let mut market = market(0, 0);
+        market.close_factor_percent = 50;
+
+        assert_eq!(market.max_liquidation_qty(101).unwrap(), 51);
+        assert_eq!(market.max_liquidation_qty(u64::MAX).unwrap(), u64::MAX / 2 + 1);
+
+        market.close_factor_percent = 100;
+        assert_eq!(market.max_liquidation_qty(u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn borrow_aggregates_fit_max_interest_rate_limit() {
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        let mut d = debt(u64::MAX, MAX_INTEREST_RATE_LIMIT, 0);
+        d.timestamp = 1 << 33;
+
+        user.add_borrow(&d).unwrap();
+        assert_eq!(get_user_total_debt(&user, 1 << 33).unwrap(), u64::MAX);
+        user.remove_borrow(&d).unwrap();
+    }
+
+    #[test]
+    fn maturity_is_set_only_for_term_markets() {
+        let mut market = market(0, 0);
+        assert_eq!(market.get_maturity(1_000), 0);
+
+        market.loan_term = 7 * 24 * 60 * 60;
+        assert_eq!(market.get_maturity(1_000), 1_000 + 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn user_is_insolvent_when_collateral_and_base_free_cannot_pay_debt() {
+        let mut market = market(0, 0);
+        market.liquidation_bonus_percent = 3;
+        let price = OraclePrice { price: 37, expo: 0 };
+
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        user.quote_total = 10;
+        user.add_borrow(&debt(400, 0, 0)).unwrap();
+
+        // 10 quote is 370 base, 359 after liquidation bonus
+        assert_eq!(get_collateral_base_value(&user, &market, price).unwrap(), 359);
+        assert!(is_user_insolvent(&user, &market, price, 0).unwrap());
+
+        user.base_free = 41;
+        assert!(!is_user_insolvent(&user, &market, price, 0).unwrap());
+    }
+
+    #[test]
+    fn collateral_is_seized_pro_rata_when_debt_is_capped() {
+        let mut market = market(0, 0);
+        market.liquidation_bonus_percent = 3;
+        let price = OraclePrice { price: 37, expo: 0 };
+
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        user.quote_total = 10;
+
+        // All collateral worth 359 base is seized when debt is larger
+        assert_eq!(get_collateral_seizure(&user, &market, price, 400).unwrap(), (359, 10));
+        assert_eq!(get_collateral_seizure(&user, &market, price, 359).unwrap(), (359, 10));
+
+        // Only debt is paid and quote is seized for it, rounded down in favor of the borrower
+        assert_eq!(get_collateral_seizure(&user, &market, price, 180).unwrap(), (180, 5));
+        assert_eq!(get_collateral_seizure(&user, &market, price, 100).unwrap(), (100, 2));
+        assert_eq!(get_collateral_seizure(&user, &market, price, 0).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn liquidation_bonus_is_flat_without_auction() {
+        let mut market = market(0, 0);
+        market.liquidation_bonus_percent = 3;
+
+        assert_eq!(market.get_liquidation_bonus_bps(0, 1_000), 300);
+        assert_eq!(market.with_liquidation_bonus(1000).unwrap(), 1030);
+        assert_eq!(market.with_bonus_bps(1000, 300).unwrap(), 1030);
+        assert!(market.with_liquidation_bonus(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn auction_bonus_grows_linearly_to_liquidation_bonus() {
+        let mut market = market(0, 0);
+        market.liquidation_bonus_percent = 4;
+        market.liquidation_auction_duration = 600;
+
+        assert_eq!(market.get_liquidation_bonus_bps(0, 1_000), 0);
+        assert_eq!(market.get_liquidation_bonus_bps(1_000, 1_000), 0);
+        assert_eq!(market.get_liquidation_bonus_bps(1_000, 1_150), 100);
+        assert_eq!(market.get_liquidation_bonus_bps(1_000, 1_300), 200);
+        assert_eq!(market.get_liquidation_bonus_bps(1_000, 1_600), 400);
+        assert_eq!(market.get_liquidation_bonus_bps(1_000, 5_000), 400);
+        assert_eq!(market.with_bonus_bps(1000, 200).unwrap(), 1020);
+    }
+
+    #[test]
+    fn health_after_liquid_takes_the_given_bonus() {
+        let mut market = market(0, 0);
+        market.liquidation_bonus_percent = 10;
+        let price = OraclePrice { price: 1, expo: 0 };
+
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+        user.quote_total = 1000;
+        user.add_borrow(&debt(1000, 0, 0)).unwrap();
+
+        // 500 quote is left for 500 debt without bonus, 450 with 10% bonus
+        assert_eq!(get_user_health_factor_after_liquid(500, 0, &user, &market, price, 0).unwrap(), 100);
+        assert_eq!(get_user_health_factor_after_liquid(500, 1000, &user, &market, price, 0).unwrap(), 90);
+        assert!(get_user_health_factor_after_liquid(1001, 0, &user, &market, price, 0).is_err());
+    }
+
+    #[test]
+    fn liquidation_auction_opens_once_and_closes_when_healthy() {
+        let mut user: UserAccount = unsafe { std::mem::zeroed() };
+
+        assert!(!refresh_liquidation_auction(&mut user, 100, 1_000));
+        assert!(refresh_liquidation_auction(&mut user, 99, 1_000));
+        assert!(!refresh_liquidation_auction(&mut user, 80, 2_000));
+        assert_eq!({ user.liquidation_auction_start }, 1_000);
+
+        assert!(refresh_liquidation_auction(&mut user, 120, 3_000));
+        assert_eq!({ user.liquidation_auction_start }, 0);
+    }
+
+    #[test]
+    fn liquidation_auction_ends_with_last_debt() {
+        let (mut user, mut tail) = user_with_capacity(1, 2);
+        user.add_debt(&mut tail, 1).unwrap();
+        user.add_debt(&mut tail, 2).unwrap();
+        user.liquidation_auction_start = 1_000;
+
+        user.remove_debt(&mut tail, 1).unwrap();
+        assert_eq!({ user.liquidation_auction_start }, 1_000);
+
+        user.remove_debt(&mut tail, 2).unwrap();
+        assert_eq!({ user.liquidation_auction_start }, 0);
+    }
+
+    #[test]
+    fn crank_reward_is_share_of_budget_by_consumed_events() {
+        let mut market = market(0, 0);
+        market.crank_fee_budget = 10000;
+
+        assert_eq!(market.take_crank_reward(1, 3), 3333);
+        assert_eq!(market.take_crank_reward(1, 2), 3333);
+        assert_eq!(market.take_crank_reward(5, 1), 3334);
+        assert_eq!({ market.crank_fee_budget }, 0);
+        assert_eq!(market.take_crank_reward(1, 0), 0);
+    }
+
+    #[test]
+    fn same_decimals_integer_price() {
+        let market = market(0, 0);
+        let price = OraclePrice { price: 37, expo: 0 };
+        assert_eq!(price.quote_to_base(10, &market).unwrap(), 370);
+        assert_eq!(price.base_to_quote_ceil(100, &market).unwrap(), 3);
+    }
+
+    #[test]
+    fn negative_expo_keeps_precision() {
+        // SOL/USD of 150.12345678 with both sides having no decimals
+        let market = market(0, 0);
+        let price = OraclePrice { price: 15012345678, expo: -8 };
+        assert_eq!(price.quote_to_base(100, &market).unwrap(), 15012);
+    }
+
+    #[test]
+    fn usdc_base_sol_quote() {
+        // Base is USDC with 6 decimals and quote is SOL with 9 decimals
+        let market = market(6, 9);
+        let price = OraclePrice { price: 15012345678, expo: -8 };
+
+        // 1 SOL is 150.123456 USDC
+        assert_eq!(price.quote_to_base(1_000_000_000, &market).unwrap(), 150_123_456);
+        assert_eq!(price.base_to_quote_ceil(150_123_456, &market).unwrap(), 999_999_995);
+        assert_eq!(price.base_to_quote_ceil(150_123_457, &market).unwrap(), 1_000_000_002);
+    }
+
+    #[test]
+    fn positive_expo() {
+        let market = market(9, 6);
+        let price = OraclePrice { price: 2, expo: 1 };
+        assert_eq!(price.quote_to_base(1, &market).unwrap(), 20_000);
+        assert_eq!(price.base_to_quote_ceil(20_001, &market).unwrap(), 2);
+    }
+}
@@ -125,11 +125,16 @@ const setup = async () => {
 
     const oracleType = { stub: {} }
     const oraclePubkey = stubPriceOracle.publicKey;
+    // Open-ended loans, set seconds for a fixed-term market
+    const loanTerm = new anchor.BN(0);
 
-    const tx = await program.rpc.initializeMarket(signerBump, mintBase.publicKey, mintQuote.publicKey, oracleType, {
+    const tx = await program.rpc.initializeMarket(signerBump, oracleType, loanTerm, {
       accounts: {
         admin: admin.publicKey,
         market: lexMarket.publicKey,
+        marketSigner: marketSigner,
+        baseMint: mintBase.publicKey,
+        quoteMint: mintQuote.publicKey,
         baseVault: lexBaseVault,
         quoteVault: lexQuoteVault,
         eventQueue: eventQueue.publicKey,
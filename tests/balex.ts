@@ -19,6 +19,8 @@ describe('balex', () => {
   let mintQuote: spl_token.Token;
 
   let stubPriceOracle = anchor.web3.Keypair.generate();
+  // Oracle controlled by an attacker, program should never accept it in place of market oracle
+  let fakePriceOracle = anchor.web3.Keypair.generate();
 
   // Market accounts
   const NODE_CAPACITY = 100;
@@ -32,6 +34,9 @@ describe('balex', () => {
   let lexMarket = anchor.web3.Keypair.generate();
   let admin = anchor.web3.Keypair.generate();
 
+  // Loans of the test market are open-ended, so debts have no maturity
+  const loanTerm = new anchor.BN(0);
+
   let marketSigner: anchor.web3.PublicKey;
   let signerBump: number;
 
@@ -52,6 +57,47 @@ describe('balex', () => {
   let aliceBump: number;
   let bobUserAccount: anchor.web3.PublicKey;
   let bobBump: number;
+
+  let firstDebt: anchor.web3.PublicKey;
+
+  // Carol only borrows from Alice and Bob and becomes insolvent
+  let carol = anchor.web3.Keypair.generate();
+  let carolAccountBase: anchor.web3.PublicKey;
+  let carolAccountQuote: anchor.web3.PublicKey;
+  let carolUserAccount: anchor.web3.PublicKey;
+  let carolBump: number;
+
+  const U64_MAX = new anchor.BN("18446744073709551615");
+
+  // Open orders are stored after the user account fields, discriminator + 136 bytes of
+  // owner (32), seven u64 balances (56), two u128 rate sums (32), auction start (8) and four u16 counters (8),
+  // each one is order id (u128), expiry timestamp (i64) and client order id (u64)
+  const USER_ACCOUNT_TAIL_OFFSET = 8 + 32 + 7 * 8 + 2 * 16 + 8 + 4 * 2;
+  const OPEN_ORDER_LEN = 32;
+
+  const limitOrder = { limit: {} };
+  const abortSelfTrade = { abortTransaction: {} };
+
+  async function getOpenOrder(userAccount: anchor.web3.PublicKey, i: number): Promise<anchor.BN> {
+    let data = (await connection.getAccountInfo(userAccount)).data;
+    let offset = USER_ACCOUNT_TAIL_OFFSET + OPEN_ORDER_LEN * i;
+    return new anchor.BN(data.slice(offset, offset + 16), 'le');
+  }
+
+  // Same as Debt::repay, interest is rate percent per hour of qty since debt timestamp and it's paid first
+  const INTEREST_RATE_DENOMINATOR = new anchor.BN(60 * 60 * 100);
+  function repaidDebt(debt: any, amount: anchor.BN, now: anchor.BN): { qty: anchor.BN, accruedInterest: anchor.BN } {
+    let elapsed = anchor.BN.max(now.sub(debt.timestamp), new anchor.BN(0));
+    let principal = anchor.BN.max(debt.qty.sub(debt.liquidQty), new anchor.BN(0));
+    let interest = debt.accruedInterest
+      .add(debt.qty.mul(debt.interestRate).mul(elapsed).div(INTEREST_RATE_DENOMINATOR))
+      .sub(anchor.BN.max(debt.liquidQty.sub(debt.qty), new anchor.BN(0)));
+    interest = anchor.BN.max(interest, new anchor.BN(0));
+
+    let repaid = anchor.BN.min(amount, principal.add(interest));
+    let interestPaid = anchor.BN.min(repaid, interest);
+    return { qty: principal.sub(repaid.sub(interestPaid)), accruedInterest: interest.sub(interestPaid) };
+  }
   
 
   it('Is setup!', async () => {
@@ -135,10 +181,37 @@ describe('balex', () => {
     // const oracleType = { pyth: {} }
     // const oraclePubkey = new anchor.web3.PublicKey("GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU")
 
-    const tx = await program.rpc.initializeMarket(signerBump, mintBase.publicKey, mintQuote.publicKey, oracleType, {
+    console.log("Ensure vaults should be owned by market signer");
+    let adminQuoteVault = await mintQuote.createAccount(admin.publicKey);
+    await assert.rejects(
+      program.rpc.initializeMarket(signerBump, oracleType, loanTerm, {
+        accounts: {
+          admin: admin.publicKey,
+          market: lexMarket.publicKey,
+          marketSigner: marketSigner,
+          baseMint: mintBase.publicKey,
+          quoteMint: mintQuote.publicKey,
+          baseVault: lexBaseVault,
+          quoteVault: adminQuoteVault,
+          eventQueue: eventQueue.publicKey,
+          orderbook: orderbook.publicKey,
+          priceOracle: oraclePubkey,
+          asks: asks.publicKey,
+          bids: bids.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId
+        },
+        signers: [admin, lexMarket],
+        preInstructions: [await program.account.lexMarket.createInstruction(lexMarket)]
+      })
+    );
+
+    const tx = await program.rpc.initializeMarket(signerBump, oracleType, loanTerm, {
       accounts: {
         admin: admin.publicKey,
         market: lexMarket.publicKey,
+        marketSigner: marketSigner,
+        baseMint: mintBase.publicKey,
+        quoteMint: mintQuote.publicKey,
         baseVault: lexBaseVault,
         quoteVault: lexQuoteVault,
         eventQueue: eventQueue.publicKey,
@@ -154,6 +227,97 @@ describe('balex', () => {
 
     // let lexMarketAccount = await program.account.lexMarket.fetch(lexMarket.publicKey);
     // console.log(lexMarketAccount);
+
+    console.log("Create fake Stub Price account");
+    await program.rpc.setStubPrice(new anchor.BN(100000), new anchor.BN(10), {
+      accounts: {
+        admin: alice.publicKey,
+        stubPrice: fakePriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [alice, fakePriceOracle]
+    })
+
+    console.log("Ensure only oracle authority can move stub price");
+    await assert.rejects(
+      program.rpc.setStubPrice(new anchor.BN(1), new anchor.BN(0), {
+        accounts: {
+          admin: alice.publicKey,
+          stubPrice: stubPriceOracle.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }, signers: [alice]
+      })
+    );
+    let stubPriceData = await program.account.stubPrice.fetch(stubPriceOracle.publicKey);
+    assert.ok(stubPriceData.price.eqn(100));
+    assert.ok(stubPriceData.authority.equals(admin.publicKey));
+  });
+
+  it('Admin updates market params', async () => {
+    const params = {
+      overCollateralPercent: 50,
+      liquidationBonusPercent: 3,
+      closeFactorPercent: 50,
+      minOrderSize: new anchor.BN(1),
+      maxInterestRate: new anchor.BN(100),
+      maxConfidenceBps: new anchor.BN(200),
+      maxPriceStalenessSlots: new anchor.BN(25),
+      protocolFeePercent: 10,
+      crankFee: new anchor.BN(5000),
+      gracePeriod: new anchor.BN(24 * 60 * 60),
+      insurancePercent: 0,
+      liquidationAuctionDuration: new anchor.BN(0),
+    };
+
+    console.log("Ensure only admin can update params");
+    await assert.rejects(
+      program.rpc.updateMarketParams(params, {
+        accounts: {
+          riskAdmin: alice.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [alice]
+      })
+    );
+
+    console.log("Ensure invalid params are rejected");
+    await assert.rejects(
+      program.rpc.updateMarketParams({...params, closeFactorPercent: 101}, {
+        accounts: {
+          riskAdmin: admin.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [admin]
+      })
+    );
+    await assert.rejects(
+      program.rpc.updateMarketParams({...params, insurancePercent: 101}, {
+        accounts: {
+          riskAdmin: admin.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [admin]
+      })
+    );
+    await assert.rejects(
+      program.rpc.updateMarketParams({...params, maxInterestRate: new anchor.BN(1_000_001)}, {
+        accounts: {
+          riskAdmin: admin.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [admin]
+      })
+    );
+
+    await program.rpc.updateMarketParams(params, {
+      accounts: {
+        riskAdmin: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+
+    let lexMarketAccount = await program.account.lexMarket.fetch(lexMarket.publicKey);
+    assert.equal(lexMarketAccount.maxInterestRate.toNumber(), 100);
   });
 
   it('Initialize user accounts', async () => {
@@ -181,6 +345,52 @@ describe('balex', () => {
     });
   });
 
+  it('Alice resizes her user account', async () => {
+    let oldSize = (await connection.getAccountInfo(aliceUserAccount)).data.length;
+
+    await assert.rejects(
+      program.rpc.resizeUserAccount(aliceBump, 2000, 16, {
+        accounts: {
+          owner: alice.publicKey,
+          userAccount: aliceUserAccount,
+          market: lexMarket.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId
+        },
+        signers: [alice]
+      })
+    );
+
+    await program.rpc.resizeUserAccount(aliceBump, 32, 24, {
+      accounts: {
+        owner: alice.publicKey,
+        userAccount: aliceUserAccount,
+        market: lexMarket.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      },
+      signers: [alice]
+    });
+
+    let aliceUserAccountData = await program.account.userAccount.fetch(aliceUserAccount);
+    assert.equal(aliceUserAccountData.openOrdersCapacity, 32);
+    assert.equal(aliceUserAccountData.openDebtsCapacity, 24);
+
+    let newSize = (await connection.getAccountInfo(aliceUserAccount)).data.length;
+    assert.equal(newSize, oldSize + 16 * OPEN_ORDER_LEN + 8 * 8);
+
+    console.log("Ensure only legacy user accounts can be migrated");
+    await assert.rejects(
+      program.rpc.migrateUserAccount(aliceBump, {
+        accounts: {
+          owner: alice.publicKey,
+          userAccount: aliceUserAccount,
+          market: lexMarket.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId
+        },
+        signers: [alice]
+      })
+    );
+  });
+
   it('Invalid deposit', async() => {
     await assert.rejects(
       program.rpc.deposit(bobBump, new anchor.BN(10), {
@@ -197,6 +407,22 @@ describe('balex', () => {
       )
     );
 
+    console.log("Ensure token source should have mint of vault");
+    await assert.rejects(
+      program.rpc.deposit(bobBump, new anchor.BN(10), {
+          accounts: {
+            owner: bob.publicKey,
+            userAccount: bobUserAccount,
+            market: lexMarket.publicKey,
+            vault: lexBaseVault,
+            tokenSource: bobAccountQuote,
+            tokenProgram: spl_token.TOKEN_PROGRAM_ID
+          },
+          signers: [bob]
+        }
+      )
+    );
+
     await assert.rejects(
       program.rpc.deposit(bobBump, new anchor.BN(1000000), {
           accounts: {
@@ -258,11 +484,156 @@ describe('balex', () => {
     assert.equal((await program.account.userAccount.fetch(bobUserAccount)).quoteTotal.toNumber(), 10);
   });
 
+  it('Admin hands over the market in two steps', async () => {
+    console.log("Ensure only admin can propose a new admin");
+    await assert.rejects(
+      program.rpc.proposeAdmin(alice.publicKey, {
+        accounts: {
+          admin: alice.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [alice]
+      })
+    );
+
+    await program.rpc.proposeAdmin(alice.publicKey, {
+      accounts: {
+        admin: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+
+    console.log("Ensure only proposed admin can accept");
+    await assert.rejects(
+      program.rpc.acceptAdmin({
+        accounts: {
+          pendingAdmin: bob.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [bob]
+      })
+    );
+
+    await program.rpc.acceptAdmin({
+      accounts: {
+        pendingAdmin: alice.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [alice]
+    });
+    let lexMarketData = await program.account.lexMarket.fetch(lexMarket.publicKey);
+    assert.ok(lexMarketData.admin.equals(alice.publicKey));
+    assert.ok(lexMarketData.pendingAdmin.equals(anchor.web3.PublicKey.default));
+
+    console.log("Ensure admin can't do what other roles do");
+    await assert.rejects(
+      program.rpc.setMarketStatus({paused: {}}, {
+        accounts: {
+          authority: alice.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [alice]
+      })
+    );
+
+    await program.rpc.proposeAdmin(admin.publicKey, {
+      accounts: {
+        admin: alice.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [alice]
+    });
+    await program.rpc.acceptAdmin({
+      accounts: {
+        pendingAdmin: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+    assert.ok((await program.account.lexMarket.fetch(lexMarket.publicKey)).admin.equals(admin.publicKey));
+  });
+
+  it('Guardian pauses the market and risk admin resumes it', async () => {
+    await program.rpc.setRoles(admin.publicKey, admin.publicKey, alice.publicKey, {
+      accounts: {
+        admin: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+
+    console.log("Ensure only risk admin and guardian can pause");
+    await assert.rejects(
+      program.rpc.setMarketStatus({paused: {}}, {
+        accounts: {
+          authority: bob.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [bob]
+      })
+    );
+
+    await program.rpc.setMarketStatus({paused: {}}, {
+      accounts: {
+        authority: alice.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [alice]
+    });
+    assert.ok('paused' in (await program.account.lexMarket.fetch(lexMarket.publicKey)).status);
+
+    console.log("Ensure nothing but cancels works on paused market");
+    await assert.rejects(
+      program.rpc.deposit(aliceBump, new anchor.BN(10), {
+        accounts: {
+          owner: alice.publicKey,
+          userAccount: aliceUserAccount,
+          market: lexMarket.publicKey,
+          vault: lexBaseVault,
+          tokenSource: aliceAccountBase,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice]
+      })
+    );
+
+    console.log("Ensure guardian can't resume the market");
+    await assert.rejects(
+      program.rpc.setMarketStatus({active: {}}, {
+        accounts: {
+          authority: alice.publicKey,
+          market: lexMarket.publicKey,
+        },
+        signers: [alice]
+      })
+    );
+
+    await program.rpc.setMarketStatus({reduceOnly: {}}, {
+      accounts: {
+        authority: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+    assert.ok('reduceOnly' in (await program.account.lexMarket.fetch(lexMarket.publicKey)).status);
+
+    await program.rpc.setMarketStatus({active: {}}, {
+      accounts: {
+        authority: admin.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [admin]
+    });
+    assert.ok('active' in (await program.account.lexMarket.fetch(lexMarket.publicKey)).status);
+  });
+
   it('Alice creates Ask order', async () => {
     let rate = new anchor.BN(3);
     let qty = new anchor.BN(500)
     let askType = 1;
-    await program.rpc.newOrder(aliceBump, askType, rate, qty, {
+
+    await program.rpc.newOrder(aliceBump, askType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
 
       accounts: {
         owner: alice.publicKey,
@@ -286,7 +657,7 @@ describe('balex', () => {
     let qty = new anchor.BN(1000)
     let bidType = 0;
     await assert.rejects(
-      program.rpc.newOrder(bobBump, bidType, rate, qty, {
+      program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
         accounts: {
           owner: bob.publicKey,
           userAccount: bobUserAccount,
@@ -301,8 +672,25 @@ describe('balex', () => {
       })
     );
 
+    console.log("Ensure fake oracle can't be used to borrow more");
+    await assert.rejects(
+      program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+        accounts: {
+          owner: bob.publicKey,
+          userAccount: bobUserAccount,
+          market: lexMarket.publicKey,
+          eventQueue: eventQueue.publicKey,
+          orderbook: orderbook.publicKey,
+          asks: asks.publicKey,
+          bids: bids.publicKey,
+          priceOracle: fakePriceOracle.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId
+        }, signers: [bob]
+      })
+    );
+
     qty = new anchor.BN(300);
-    await program.rpc.newOrder(bobBump, bidType, rate, qty, {
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
         accounts: {
           owner: bob.publicKey,
           userAccount: bobUserAccount,
@@ -318,23 +706,55 @@ describe('balex', () => {
   });
 
   it('Wallet owner consumes events', async () => {
+    [firstDebt] = await anchor.web3.PublicKey.findProgramAddress([lexMarket.publicKey.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, 'le', 8)], program.programId);
+
+    // Both orders paid crank fee which goes to the cranker
+    let rewardTarget = admin.publicKey;
+    let rewardTargetBalance = await connection.getBalance(rewardTarget);
+    let crankFeeBudget = (await program.account.lexMarket.fetch(lexMarket.publicKey)).crankFeeBudget.toNumber();
+    assert.equal(crankFeeBudget, 10000);
+
+    let debtCreated = new Promise<any>((resolve) => {
+      let listener = program.addEventListener("DebtCreated", (event, _slot) => {
+        program.removeEventListener(listener);
+        resolve(event);
+      });
+    });
+
     await program.rpc.consumeOrderEvents(new anchor.BN(10), {
       accounts: {
         market: lexMarket.publicKey,
         eventQueue: eventQueue.publicKey,
         orderbook: orderbook.publicKey,
+        payer: provider.wallet.publicKey,
+        rewardTarget: rewardTarget,
         systemProgram: anchor.web3.SystemProgram.programId
       },
       remainingAccounts: [
         {pubkey: aliceUserAccount, isSigner: false, isWritable: true},
         {pubkey: bobUserAccount, isSigner: false, isWritable: true},
+        {pubkey: firstDebt, isSigner: false, isWritable: true},
       ]
     });
+
+    let debtData = await program.account.debt.fetch(firstDebt);
+    assert.ok(debtData.id.eqn(0));
+    assert.ok(debtData.borrower.equals(bob.publicKey));
+    assert.ok(debtData.lender.equals(alice.publicKey));
+    assert.ok(debtData.maturity.eqn(0));
+
+    let debtCreatedEvent = await debtCreated;
+    assert.ok(debtCreatedEvent.debtId.eqn(0));
+    assert.ok(debtCreatedEvent.lender.equals(alice.publicKey));
+    assert.ok(debtCreatedEvent.borrower.equals(bob.publicKey));
+    assert.ok(debtCreatedEvent.qty.eq(debtData.qty));
+
+    assert.equal(await connection.getBalance(rewardTarget), rewardTargetBalance + crankFeeBudget);
+    assert.equal((await program.account.lexMarket.fetch(lexMarket.publicKey)).crankFeeBudget.toNumber(), 0);
   });
 
   it('Alice cancels remaining of her first order', async () => {
-    let aliceUserAccountData = await program.account.userAccount.fetch(aliceUserAccount);
-    let order_id = aliceUserAccountData.openOrders[0]
+    let order_id = await getOpenOrder(aliceUserAccount, 0);
 
     console.log("Ensure bob cannot cancel her order");
     await assert.rejects(
@@ -352,6 +772,8 @@ describe('balex', () => {
       })
     );
 
+    let aliceUserAccountDataBefore = await program.account.userAccount.fetch(aliceUserAccount);
+
     console.log("Cancelling order");
     await program.rpc.cancelMyOrder(aliceBump, order_id, {
       accounts: {
@@ -366,8 +788,12 @@ describe('balex', () => {
       }, signers: [alice]
     });
 
-    aliceUserAccountData = await program.account.userAccount.fetch(aliceUserAccount);
+    let aliceUserAccountData = await program.account.userAccount.fetch(aliceUserAccount);
     assert.equal(aliceUserAccountData.baseOpenLend, 0); 
+    assert.equal(
+      aliceUserAccountData.baseFree.toNumber(),
+      aliceUserAccountDataBefore.baseFree.toNumber() + aliceUserAccountDataBefore.baseOpenLend.toNumber()
+    );
 
     console.log("Ensure cannot cancel order twice");
     await assert.rejects(
@@ -387,12 +813,27 @@ describe('balex', () => {
   });
 
 
-  it('Bob set another order which becomes risky and will be cancelled', async () => {
-    let rate = new anchor.BN(4);
-    let qty = new anchor.BN(100)
+  it('Anyone purges expired orders', async () => {
+    let rate = new anchor.BN(1);
+    let qty = new anchor.BN(1);
     let bidType = 0;
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+
+    const purgeAccounts = {
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
 
-    await program.rpc.newOrder(bobBump, bidType, rate, qty, {
+    let now = await connection.getBlockTime(await connection.getSlot());
+
+    console.log("Ensure expiry timestamp should be in the future");
+    await assert.rejects(
+      program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(now - 1), limitOrder, abortSelfTrade, new anchor.BN(0), {
         accounts: {
           owner: bob.publicKey,
           userAccount: bobUserAccount,
@@ -404,17 +845,381 @@ describe('balex', () => {
           priceOracle: stubPriceOracle.publicKey,
           systemProgram: anchor.web3.SystemProgram.programId
         }, signers: [bob]
+      })
+    );
+
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(now + 2), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: {
+        owner: bob.publicKey,
+        userAccount: bobUserAccount,
+        market: lexMarket.publicKey,
+        eventQueue: eventQueue.publicKey,
+        orderbook: orderbook.publicKey,
+        asks: asks.publicKey,
+        bids: bids.publicKey,
+        priceOracle: stubPriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      }, signers: [bob]
     });
 
-    // Set price to high so it be considered risky
-    // Bob has 300 borrowed in place + 100 request with 10 btc
+    console.log("Ensure order can't be purged before expiry");
+    await assert.rejects(
+      program.rpc.purgeExpiredOrders(10, {accounts: purgeAccounts})
+    );
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    await program.rpc.purgeExpiredOrders(10, {accounts: purgeAccounts});
+
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt);
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow));
+  });
 
+  it('Bob cancels his order by client order id', async () => {
+    let rate = new anchor.BN(1);
+    let qty = new anchor.BN(1);
+    let bidType = 0;
+    let clientOrderId = new anchor.BN(42);
     let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
 
-    let order_id = bobUserAccountData.openOrders[0]
+    const orderAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+    const cancelAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, clientOrderId, {
+      accounts: orderAccounts, signers: [bob]
+    });
 
+    console.log("Ensure client order id can't be reused by another open order");
     await assert.rejects(
-      program.rpc.cancelRiskyOrder(bobBump, order_id, {
+      program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, clientOrderId, {
+        accounts: orderAccounts, signers: [bob]
+      })
+    );
+
+    await program.rpc.cancelByClientOrderId(bobBump, clientOrderId, {
+      accounts: cancelAccounts, signers: [bob]
+    });
+
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt);
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow));
+
+    console.log("Ensure cannot cancel by client order id twice");
+    await assert.rejects(
+      program.rpc.cancelByClientOrderId(bobBump, clientOrderId, {
+        accounts: cancelAccounts, signers: [bob]
+      })
+    );
+  });
+
+  it('Alice re-quotes with replace order and cancels all her orders', async () => {
+    let askType = 1;
+    let aliceUserAccountData = await program.account.userAccount.fetch(aliceUserAccount);
+
+    const orderAccounts = {
+      owner: alice.publicKey,
+      userAccount: aliceUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+    const cancelAccounts = {
+      owner: alice.publicKey,
+      userAccount: aliceUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    await program.rpc.newOrder(aliceBump, askType, new anchor.BN(50), new anchor.BN(10), new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [alice]
+    });
+    let order_id = await getOpenOrder(aliceUserAccount, 0);
+
+    await program.rpc.replaceOrder(aliceBump, order_id, new anchor.BN(60), new anchor.BN(20), new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [alice]
+    });
+
+    let aliceUserAccountDataAfter = await program.account.userAccount.fetch(aliceUserAccount);
+    assert.equal(aliceUserAccountDataAfter.openOrdersCnt, 1);
+    assert.equal(aliceUserAccountDataAfter.baseOpenLend.toNumber(), 20);
+    assert.equal(aliceUserAccountDataAfter.baseFree.toNumber(), aliceUserAccountData.baseFree.toNumber() - 20);
+    assert.ok(!(await getOpenOrder(aliceUserAccount, 0)).eq(order_id));
+
+    console.log("Ensure only orders of the given side are cancelled");
+    await program.rpc.cancelAllOrders(aliceBump, 0, {
+      accounts: cancelAccounts, signers: [alice]
+    });
+    assert.equal((await program.account.userAccount.fetch(aliceUserAccount)).openOrdersCnt, 1);
+
+    await program.rpc.cancelAllOrders(aliceBump, null, {
+      accounts: cancelAccounts, signers: [alice]
+    });
+
+    aliceUserAccountDataAfter = await program.account.userAccount.fetch(aliceUserAccount);
+    assert.equal(aliceUserAccountDataAfter.openOrdersCnt, 0);
+    assert.equal(aliceUserAccountDataAfter.baseOpenLend.toNumber(), 0);
+    assert.ok(aliceUserAccountDataAfter.baseFree.eq(aliceUserAccountData.baseFree));
+  });
+
+  it('Bob borrows right now with immediate-or-cancel and fill-or-kill orders', async () => {
+    let rate = new anchor.BN(4);
+    let qty = new anchor.BN(10);
+    let bidType = 0;
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+
+    const orderAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    console.log("Ensure fill-or-kill order is rejected when there's no ask to fill it");
+    await assert.rejects(
+      program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), { fillOrKill: {} }, abortSelfTrade, new anchor.BN(0), {
+        accounts: orderAccounts, signers: [bob]
+      })
+    );
+
+    // Nothing to match, so the order is cancelled instead of resting on the book
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), { immediateOrCancel: {} }, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt);
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow));
+  });
+
+  it('Post-only order is rejected when it would match', async () => {
+    let qty = new anchor.BN(10);
+    let askType = 1;
+    let bidType = 0;
+    const postOnly = { postOnly: {} };
+
+    const orderAccounts = {
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+    const cancelAccounts = {
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    await program.rpc.newOrder(aliceBump, askType, new anchor.BN(5), qty, new anchor.BN(0), postOnly, abortSelfTrade, new anchor.BN(0), {
+      accounts: {...orderAccounts, owner: alice.publicKey, userAccount: aliceUserAccount}, signers: [alice]
+    });
+
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+    await assert.rejects(
+      program.rpc.newOrder(bobBump, bidType, new anchor.BN(5), qty, new anchor.BN(0), postOnly, abortSelfTrade, new anchor.BN(0), {
+        accounts: {...orderAccounts, owner: bob.publicKey, userAccount: bobUserAccount}, signers: [bob]
+      })
+    );
+
+    // Lower rate doesn't cross the ask, so it rests on the book
+    await program.rpc.newOrder(bobBump, bidType, new anchor.BN(3), qty, new anchor.BN(0), postOnly, abortSelfTrade, new anchor.BN(0), {
+      accounts: {...orderAccounts, owner: bob.publicKey, userAccount: bobUserAccount}, signers: [bob]
+    });
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt + 1);
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow.add(qty)));
+
+    await program.rpc.cancelAllOrders(aliceBump, null, {
+      accounts: {...cancelAccounts, owner: alice.publicKey, userAccount: aliceUserAccount}, signers: [alice]
+    });
+    await program.rpc.cancelAllOrders(bobBump, null, {
+      accounts: {...cancelAccounts, owner: bob.publicKey, userAccount: bobUserAccount}, signers: [bob]
+    });
+    assert.equal((await program.account.userAccount.fetch(bobUserAccount)).openOrdersCnt, bobUserAccountData.openOrdersCnt);
+  });
+
+  it('Bob self trades with decrement-take without borrowing from himself', async () => {
+    let rate = new anchor.BN(4);
+    let qty = new anchor.BN(10);
+    let askType = 1;
+    let bidType = 0;
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+    let nextDebtId = (await program.account.lexMarket.fetch(lexMarket.publicKey)).nextDebtId;
+
+    const orderAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    await program.rpc.newOrder(bobBump, askType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, { decrementTake: {} }, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+
+    await program.rpc.consumeOrderEvents(new anchor.BN(10), {
+      accounts: {
+        market: lexMarket.publicKey,
+        eventQueue: eventQueue.publicKey,
+        orderbook: orderbook.publicKey,
+        payer: provider.wallet.publicKey,
+        rewardTarget: admin.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      },
+      remainingAccounts: [
+        {pubkey: aliceUserAccount, isSigner: false, isWritable: true},
+        {pubkey: bobUserAccount, isSigner: false, isWritable: true},
+      ]
+    });
+
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt);
+    assert.equal(bobUserAccountDataAfter.openDebtsCnt, bobUserAccountData.openDebtsCnt);
+    assert.ok(bobUserAccountDataAfter.baseFree.eq(bobUserAccountData.baseFree));
+    assert.ok(bobUserAccountDataAfter.baseOpenLend.eq(bobUserAccountData.baseOpenLend));
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow));
+    assert.ok((await program.account.lexMarket.fetch(lexMarket.publicKey)).nextDebtId.eq(nextDebtId));
+  });
+
+  it('Purge skips expired orders which are matched but not consumed yet', async () => {
+    let askType = 1;
+    let bidType = 0;
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+    let now = await connection.getBlockTime(await connection.getSlot());
+
+    const orderAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+    const purgeAccounts = {
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+
+    // The ask is taken out of the orderbook by the self trade while its event is still in the queue
+    await program.rpc.newOrder(bobBump, askType, new anchor.BN(4), new anchor.BN(10), new anchor.BN(now + 2), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+    await program.rpc.newOrder(bobBump, bidType, new anchor.BN(1), new anchor.BN(1), new anchor.BN(now + 2), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+    await program.rpc.newOrder(bobBump, bidType, new anchor.BN(4), new anchor.BN(10), new anchor.BN(0), limitOrder, { decrementTake: {} }, new anchor.BN(0), {
+      accounts: orderAccounts, signers: [bob]
+    });
+    assert.equal((await program.account.userAccount.fetch(bobUserAccount)).openOrdersCnt, bobUserAccountData.openOrdersCnt + 2);
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    await program.rpc.purgeExpiredOrders(10, {accounts: purgeAccounts});
+
+    let bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt + 1);
+    assert.ok(bobUserAccountDataAfter.baseOpenBorrow.eq(bobUserAccountData.baseOpenBorrow));
+
+    await program.rpc.consumeOrderEvents(new anchor.BN(10), {
+      accounts: {
+        market: lexMarket.publicKey,
+        eventQueue: eventQueue.publicKey,
+        orderbook: orderbook.publicKey,
+        payer: provider.wallet.publicKey,
+        rewardTarget: admin.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      },
+      remainingAccounts: [
+        {pubkey: bobUserAccount, isSigner: false, isWritable: true},
+      ]
+    });
+
+    bobUserAccountDataAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountDataAfter.openOrdersCnt, bobUserAccountData.openOrdersCnt);
+    assert.ok(bobUserAccountDataAfter.baseOpenLend.eq(bobUserAccountData.baseOpenLend));
+    assert.ok(bobUserAccountDataAfter.baseFree.eq(bobUserAccountData.baseFree));
+  });
+
+  it('Bob set another order which becomes risky and will be cancelled', async () => {
+    let rate = new anchor.BN(4);
+    let qty = new anchor.BN(100)
+    let bidType = 0;
+
+    await program.rpc.newOrder(bobBump, bidType, rate, qty, new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+        accounts: {
+          owner: bob.publicKey,
+          userAccount: bobUserAccount,
+          market: lexMarket.publicKey,
+          eventQueue: eventQueue.publicKey,
+          orderbook: orderbook.publicKey,
+          asks: asks.publicKey,
+          bids: bids.publicKey,
+          priceOracle: stubPriceOracle.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId
+        }, signers: [bob]
+    });
+
+    // Set price to high so it be considered risky
+    // Bob has 300 borrowed in place + 100 request with 10 btc
+
+    let order_id = await getOpenOrder(bobUserAccount, 0);
+
+    await assert.rejects(
+      program.rpc.cancelRiskyOrder(bobBump, order_id, {
             accounts: {
                 owner: bob.publicKey,
                 userAccount: bobUserAccount,
@@ -430,6 +1235,31 @@ describe('balex', () => {
           })
     );
 
+    console.log("Ensure fake oracle can't make bob risky");
+    await program.rpc.setStubPrice(new anchor.BN(1), new anchor.BN(0), {
+      accounts: {
+        admin: alice.publicKey,
+        stubPrice: fakePriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [alice]
+    })
+    await assert.rejects(
+      program.rpc.cancelRiskyOrder(bobBump, order_id, {
+            accounts: {
+                owner: bob.publicKey,
+                userAccount: bobUserAccount,
+                market: lexMarket.publicKey,
+                eventQueue: eventQueue.publicKey,
+                orderbook: orderbook.publicKey,
+                asks: asks.publicKey,
+                bids: bids.publicKey,
+                priceOracle: fakePriceOracle.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId
+            },
+            signers: []
+          })
+    );
+
     await program.rpc.setStubPrice(new anchor.BN(49), new anchor.BN(10), {
       accounts: {
         admin: admin.publicKey,
@@ -456,6 +1286,33 @@ describe('balex', () => {
   });
 
   it('Bob withdraws his borrowed debt', async () => {
+    const withdrawAccounts = {
+      owner: bob.publicKey,
+      userAccount: bobUserAccount,
+      market: lexMarket.publicKey,
+      marketSigner: marketSigner,
+      vault: lexBaseVault,
+      tokenDest: bobAccountBase,
+      priceOracle: stubPriceOracle.publicKey,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+
+    console.log("Ensure market signer should be the market PDA");
+    await assert.rejects(
+      program.rpc.withdraw(bobBump, new anchor.BN(300), {
+        accounts: {...withdrawAccounts, marketSigner: bob.publicKey},
+        signers: [bob]
+      })
+    );
+
+    console.log("Ensure token destination should have mint of vault");
+    await assert.rejects(
+      program.rpc.withdraw(bobBump, new anchor.BN(300), {
+        accounts: {...withdrawAccounts, tokenDest: bobAccountQuote},
+        signers: [bob]
+      })
+    );
+
     await program.rpc.withdraw(bobBump, new anchor.BN(300), {
       accounts: {
         owner: bob.publicKey,
@@ -491,6 +1348,21 @@ describe('balex', () => {
         signers: [bob]
       })
     )
+
+    console.log("Ensure fake oracle can't be used to withdraw collateral");
+    await program.rpc.setStubPrice(new anchor.BN(100000), new anchor.BN(10), {
+      accounts: {
+        admin: alice.publicKey,
+        stubPrice: fakePriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [alice]
+    })
+    await assert.rejects(
+      program.rpc.withdraw(bobBump, new anchor.BN(10), {
+        accounts: {...withdrawAccounts, vault: lexQuoteVault, tokenDest: bobAccountQuote, priceOracle: fakePriceOracle.publicKey},
+        signers: [bob]
+      })
+    );
   });
 
   it('Liquidation of Bob debts', async () => {
@@ -502,6 +1374,116 @@ describe('balex', () => {
       }, signers: [admin, stubPriceOracle]
     })
 
+    console.log("Ensure fake oracle can't be used to liquidate");
+    await program.rpc.setStubPrice(new anchor.BN(1), new anchor.BN(0), {
+      accounts: {
+        admin: alice.publicKey,
+        stubPrice: fakePriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [alice]
+    })
+    await assert.rejects(
+      program.rpc.liquidateDebts([new anchor.BN(0)], [new anchor.BN(100)], {
+        accounts: {
+          liquidator: alice.publicKey,
+          tokenBaseSrc: aliceAccountBase,
+          tokenQuoteDest: aliceAccountQuote,
+          baseVault: lexBaseVault,
+          quoteVault: lexQuoteVault,
+          marketSigner: marketSigner,
+          borrowerAccount: bobUserAccount,
+          priceOracle: fakePriceOracle.publicKey,
+          market: lexMarket.publicKey,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice],
+        remainingAccounts: [
+          {pubkey: firstDebt, isSigner: false, isWritable: true},
+          {pubkey: aliceUserAccount, isSigner: false, isWritable: true}
+        ]
+      })
+    );
+
+    console.log("Ensure liquidator gets quote with quote mint");
+    await assert.rejects(
+      program.rpc.liquidateDebts([new anchor.BN(0)], [new anchor.BN(100)], {
+        accounts: {
+          liquidator: alice.publicKey,
+          tokenBaseSrc: aliceAccountBase,
+          tokenQuoteDest: aliceAccountBase,
+          baseVault: lexBaseVault,
+          quoteVault: lexQuoteVault,
+          marketSigner: marketSigner,
+          borrowerAccount: bobUserAccount,
+          priceOracle: stubPriceOracle.publicKey,
+          market: lexMarket.publicKey,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice],
+        remainingAccounts: [
+          {pubkey: firstDebt, isSigner: false, isWritable: true},
+          {pubkey: aliceUserAccount, isSigner: false, isWritable: true}
+        ]
+      })
+    );
+
+    console.log("Ensure liquidation can't exceed close factor");
+    await assert.rejects(
+      program.rpc.liquidateDebts([new anchor.BN(0)], [new anchor.BN(200)], {
+        accounts: {
+          liquidator: alice.publicKey,
+          tokenBaseSrc: aliceAccountBase,
+          tokenQuoteDest: aliceAccountQuote,
+          baseVault: lexBaseVault,
+          quoteVault: lexQuoteVault,
+          marketSigner: marketSigner,
+          borrowerAccount: bobUserAccount,
+          priceOracle: stubPriceOracle.publicKey,
+          market: lexMarket.publicKey,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice],
+        remainingAccounts: [
+          {pubkey: firstDebt, isSigner: false, isWritable: true},
+          {pubkey: aliceUserAccount, isSigner: false, isWritable: true}
+        ]
+      })
+    );
+
+    console.log("Anyone opens liquidation auction of unhealthy bob");
+    await program.rpc.updateLiquidationAuction({
+      accounts: {
+        borrowerAccount: bobUserAccount,
+        market: lexMarket.publicKey,
+        priceOracle: stubPriceOracle.publicKey,
+      }
+    });
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+    assert.ok(bobUserAccountData.liquidationAuctionStart.toNumber() > 0);
+
+    console.log("Ensure debts are not written off while collateral can pay them");
+    await assert.rejects(
+      program.rpc.resolveBadDebt({
+        accounts: {
+          liquidator: alice.publicKey,
+          tokenBaseSrc: aliceAccountBase,
+          tokenQuoteDest: aliceAccountQuote,
+          baseVault: lexBaseVault,
+          quoteVault: lexQuoteVault,
+          marketSigner: marketSigner,
+          borrowerAccount: bobUserAccount,
+          priceOracle: stubPriceOracle.publicKey,
+          market: lexMarket.publicKey,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice],
+        remainingAccounts: [
+          {pubkey: firstDebt, isSigner: false, isWritable: true},
+          {pubkey: aliceUserAccount, isSigner: false, isWritable: true}
+        ]
+      })
+    );
+
     await program.rpc.liquidateDebts([new anchor.BN(0)], [new anchor.BN(100)], {
       accounts: {
         liquidator: alice.publicKey,
@@ -517,6 +1499,7 @@ describe('balex', () => {
       },
       signers: [alice],
       remainingAccounts: [
+        {pubkey: firstDebt, isSigner: false, isWritable: true},
         {pubkey: aliceUserAccount, isSigner: false, isWritable: true}
       ]
     })
@@ -527,12 +1510,13 @@ describe('balex', () => {
 
   it('Bob deposits remaining back and settles his debt', async () => {
     await assert.rejects(
-      program.rpc.settleDebt(bobBump, 0, {
+      program.rpc.settleDebt(bobBump, new anchor.BN(0), U64_MAX, {
         accounts: {
           owner: bob.publicKey,
           borrowerAccount: bobUserAccount,
           lenderAccount: aliceUserAccount,
-          market: lexMarket.publicKey
+          market: lexMarket.publicKey,
+          debt: firstDebt
         },
         signers: [bob]
       })
@@ -550,15 +1534,539 @@ describe('balex', () => {
       signers: [bob]
     });
 
-    await program.rpc.settleDebt(bobBump, 0, {
+    console.log("Ensure lender account should be account of debt lender");
+    await assert.rejects(
+      program.rpc.settleDebt(bobBump, new anchor.BN(0), U64_MAX, {
+        accounts: {
+          owner: bob.publicKey,
+          borrowerAccount: bobUserAccount,
+          lenderAccount: bobUserAccount,
+          market: lexMarket.publicKey,
+          debt: firstDebt
+        },
+        signers: [bob]
+      })
+    );
+
+    console.log("Bob repays part of his debt");
+    let debtBefore = await program.account.debt.fetch(firstDebt);
+    await program.rpc.settleDebt(bobBump, new anchor.BN(0), new anchor.BN(50), {
       accounts: {
         owner: bob.publicKey,
         borrowerAccount: bobUserAccount,
         lenderAccount: aliceUserAccount,
-        market: lexMarket.publicKey
+        market: lexMarket.publicKey,
+        debt: firstDebt
       },
       signers: [bob]
     });
+
+    let debtAfter = await program.account.debt.fetch(firstDebt);
+    let expected = repaidDebt(debtBefore, new anchor.BN(50), debtAfter.timestamp);
+    assert.ok(debtAfter.liquidQty.eqn(0));
+    assert.ok(debtAfter.qty.eq(expected.qty));
+    assert.ok(debtAfter.accruedInterest.eq(expected.accruedInterest));
+
+    let bobUserAccountData = await program.account.userAccount.fetch(bobUserAccount);
+    assert.equal(bobUserAccountData.baseFree.toNumber(), 150);
+    assert.equal(bobUserAccountData.openDebtsCnt, 1);
+
+    console.log("Alice repays part of bob debt from her wallet");
+    const repayAccounts = {
+      payer: alice.publicKey,
+      tokenBaseSrc: aliceAccountBase,
+      borrowerAccount: bobUserAccount,
+      lenderAccount: aliceUserAccount,
+      market: lexMarket.publicKey,
+      debt: firstDebt,
+      baseVault: lexBaseVault,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+
+    await assert.rejects(
+      program.rpc.repayFromWallet(new anchor.BN(0), new anchor.BN(20), {
+        accounts: {...repayAccounts, tokenBaseSrc: aliceAccountQuote},
+        signers: [alice]
+      })
+    );
+
+    let aliceAccountBaseBefore = (await mintBase.getAccountInfo(aliceAccountBase)).amount.toNumber();
+    await program.rpc.repayFromWallet(new anchor.BN(0), new anchor.BN(20), {
+      accounts: repayAccounts,
+      signers: [alice]
+    });
+
+    let aliceAccountBaseAfter = (await mintBase.getAccountInfo(aliceAccountBase)).amount.toNumber();
+    assert.equal(aliceAccountBaseBefore - aliceAccountBaseAfter, 20);
+
+    debtBefore = debtAfter;
+    debtAfter = await program.account.debt.fetch(firstDebt);
+    expected = repaidDebt(debtBefore, new anchor.BN(20), debtAfter.timestamp);
+    assert.ok(debtAfter.qty.eq(expected.qty));
+    assert.ok(debtAfter.accruedInterest.eq(expected.accruedInterest));
+
+    await program.rpc.settleDebt(bobBump, new anchor.BN(0), U64_MAX, {
+      accounts: {
+        owner: bob.publicKey,
+        borrowerAccount: bobUserAccount,
+        lenderAccount: aliceUserAccount,
+        market: lexMarket.publicKey,
+        debt: firstDebt
+      },
+      signers: [bob]
+    });
+
+    let debtData = await program.account.debt.fetch(firstDebt);
+    assert.ok(debtData.qty.eqn(0));
+    assert.ok(debtData.payer.equals(provider.wallet.publicKey));
+
+    console.log("Ensure only payer of the debt gets its rent back");
+    await assert.rejects(
+      program.rpc.closeDebt({
+        accounts: {
+          debt: firstDebt,
+          payer: bob.publicKey,
+        }
+      })
+    );
+
+    let debtRent = await connection.getBalance(firstDebt);
+    let payerBalanceBefore = await connection.getBalance(provider.wallet.publicKey);
+    await program.rpc.closeDebt({
+      accounts: {
+        debt: firstDebt,
+        payer: provider.wallet.publicKey,
+      }
+    });
+
+    assert.equal(await connection.getAccountInfo(firstDebt), null);
+    // Wallet pays the transaction fee of closing too
+    assert.ok(await connection.getBalance(provider.wallet.publicKey) > payerBalanceBefore + debtRent - 10000);
+  });
+
+  it('Bad debt of insolvent borrower is shared pro-rata between its lenders', async () => {
+    let rate = new anchor.BN(4);
+    let askType = 1;
+    let bidType = 0;
+
+    await connection.confirmTransaction(await connection.requestAirdrop(carol.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL));
+    carolAccountBase = await mintBase.createAssociatedTokenAccount(carol.publicKey);
+    carolAccountQuote = await mintQuote.createAssociatedTokenAccount(carol.publicKey);
+    await mintQuote.mintTo(carolAccountQuote, admin, [], 1);
+
+    [carolUserAccount, carolBump] = await anchor.web3.PublicKey.findProgramAddress([lexMarket.publicKey.toBuffer(), carol.publicKey.toBuffer()], program.programId);
+    await program.rpc.initializeAccount(carolBump, {
+      accounts: {
+        userAccount: carolUserAccount,
+        owner: carol.publicKey,
+        market: lexMarket.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      },
+      signers: [carol]
+    });
+
+    await program.rpc.setStubPrice(new anchor.BN(100000), new anchor.BN(10), {
+      accounts: {
+        admin: admin.publicKey,
+        stubPrice: stubPriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [admin, stubPriceOracle]
+    });
+
+    const depositAccounts = {
+      market: lexMarket.publicKey,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+    await program.rpc.deposit(carolBump, new anchor.BN(1), {
+      accounts: {...depositAccounts, owner: carol.publicKey, userAccount: carolUserAccount, vault: lexQuoteVault, tokenSource: carolAccountQuote},
+      signers: [carol]
+    });
+    await program.rpc.deposit(bobBump, new anchor.BN(50), {
+      accounts: {...depositAccounts, owner: bob.publicKey, userAccount: bobUserAccount, vault: lexBaseVault, tokenSource: bobAccountBase},
+      signers: [bob]
+    });
+
+    console.log("Alice and Bob lend to Carol");
+    const orderAccounts = {
+      market: lexMarket.publicKey,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      priceOracle: stubPriceOracle.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId
+    };
+    await program.rpc.newOrder(aliceBump, askType, rate, new anchor.BN(100), new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: {...orderAccounts, owner: alice.publicKey, userAccount: aliceUserAccount}, signers: [alice]
+    });
+    await program.rpc.newOrder(bobBump, askType, rate, new anchor.BN(50), new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: {...orderAccounts, owner: bob.publicKey, userAccount: bobUserAccount}, signers: [bob]
+    });
+    await program.rpc.newOrder(carolBump, bidType, rate, new anchor.BN(150), new anchor.BN(0), limitOrder, abortSelfTrade, new anchor.BN(0), {
+      accounts: {...orderAccounts, owner: carol.publicKey, userAccount: carolUserAccount}, signers: [carol]
+    });
+
+    let nextDebtId: anchor.BN = (await program.account.lexMarket.fetch(lexMarket.publicKey)).nextDebtId;
+    let [aliceDebt] = await anchor.web3.PublicKey.findProgramAddress([lexMarket.publicKey.toBuffer(), nextDebtId.toArrayLike(Buffer, 'le', 8)], program.programId);
+    let [bobDebt] = await anchor.web3.PublicKey.findProgramAddress([lexMarket.publicKey.toBuffer(), nextDebtId.addn(1).toArrayLike(Buffer, 'le', 8)], program.programId);
+
+    await program.rpc.consumeOrderEvents(new anchor.BN(10), {
+      accounts: {
+        market: lexMarket.publicKey,
+        eventQueue: eventQueue.publicKey,
+        orderbook: orderbook.publicKey,
+        payer: provider.wallet.publicKey,
+        rewardTarget: admin.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      },
+      remainingAccounts: [
+        {pubkey: aliceUserAccount, isSigner: false, isWritable: true},
+        {pubkey: bobUserAccount, isSigner: false, isWritable: true},
+        {pubkey: carolUserAccount, isSigner: false, isWritable: true},
+        {pubkey: aliceDebt, isSigner: false, isWritable: true},
+        {pubkey: bobDebt, isSigner: false, isWritable: true},
+      ]
+    });
+
+    assert.ok((await program.account.debt.fetch(aliceDebt)).lender.equals(alice.publicKey));
+    assert.ok((await program.account.debt.fetch(bobDebt)).lender.equals(bob.publicKey));
+    let carolUserAccountData = await program.account.userAccount.fetch(carolUserAccount);
+    assert.equal(carolUserAccountData.openDebtsCnt, 2);
+    assert.equal(carolUserAccountData.baseFree.toNumber(), 150);
+
+    // Only 30 of borrowed base is left to be used for her debts
+    await program.rpc.withdraw(carolBump, new anchor.BN(120), {
+      accounts: {
+        owner: carol.publicKey,
+        userAccount: carolUserAccount,
+        market: lexMarket.publicKey,
+        marketSigner: marketSigner,
+        vault: lexBaseVault,
+        tokenDest: carolAccountBase,
+        priceOracle: stubPriceOracle.publicKey,
+        tokenProgram: spl_token.TOKEN_PROGRAM_ID
+      },
+      signers: [carol]
+    });
+
+    console.log("Collateral of Carol becomes worthless");
+    await program.rpc.setStubPrice(new anchor.BN(1), new anchor.BN(0), {
+      accounts: {
+        admin: admin.publicKey,
+        stubPrice: stubPriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [admin, stubPriceOracle]
+    });
+
+    let aliceUserAccountBefore = await program.account.userAccount.fetch(aliceUserAccount);
+    let bobUserAccountBefore = await program.account.userAccount.fetch(bobUserAccount);
+    let lexMarketBefore = await program.account.lexMarket.fetch(lexMarket.publicKey);
+
+    let writtenOffEvents: any[] = [];
+    let writtenOffListener = program.addEventListener("DebtWrittenOff", (event, _slot) => {
+      writtenOffEvents.push(event);
+    });
+    let badDebtResolved = new Promise<any>((resolve) => {
+      let listener = program.addEventListener("BadDebtResolved", (event, _slot) => {
+        program.removeEventListener(listener);
+        resolve(event);
+      });
+    });
+
+    await program.rpc.resolveBadDebt({
+      accounts: {
+        liquidator: alice.publicKey,
+        tokenBaseSrc: aliceAccountBase,
+        tokenQuoteDest: aliceAccountQuote,
+        baseVault: lexBaseVault,
+        quoteVault: lexQuoteVault,
+        marketSigner: marketSigner,
+        borrowerAccount: carolUserAccount,
+        priceOracle: stubPriceOracle.publicKey,
+        market: lexMarket.publicKey,
+        tokenProgram: spl_token.TOKEN_PROGRAM_ID
+      },
+      signers: [alice],
+      remainingAccounts: [
+        {pubkey: aliceDebt, isSigner: false, isWritable: true},
+        {pubkey: bobDebt, isSigner: false, isWritable: true},
+        {pubkey: aliceUserAccount, isSigner: false, isWritable: true},
+        {pubkey: bobUserAccount, isSigner: false, isWritable: true},
+      ]
+    });
+
+    let resolvedEvent = await badDebtResolved;
+    await program.removeEventListener(writtenOffListener);
+
+    // Base free of Carol and insurance are all what is recovered, her collateral is worth nothing
+    let insuranceUsed = resolvedEvent.insuranceUsed.toNumber();
+    let recovered = resolvedEvent.recovered.toNumber();
+    let totalDebt = resolvedEvent.totalDebt.toNumber();
+    assert.ok(resolvedEvent.borrower.equals(carol.publicKey));
+    assert.equal(resolvedEvent.seizedQuote.toNumber(), 1);
+    assert.equal(recovered, 30 + insuranceUsed);
+    assert.equal(resolvedEvent.shortfall.toNumber(), totalDebt - recovered);
+
+    assert.equal(writtenOffEvents.length, 2);
+    let [aliceWrittenOff, bobWrittenOff] = writtenOffEvents;
+    assert.ok(aliceWrittenOff.lender.equals(alice.publicKey));
+    assert.ok(bobWrittenOff.lender.equals(bob.publicKey));
+    let aliceDebtQty = aliceWrittenOff.recovered.toNumber() + aliceWrittenOff.loss.toNumber();
+    let bobDebtQty = bobWrittenOff.recovered.toNumber() + bobWrittenOff.loss.toNumber();
+    assert.equal(aliceDebtQty + bobDebtQty, totalDebt);
+    assert.equal(aliceWrittenOff.recovered.toNumber(), Math.floor(recovered * aliceDebtQty / totalDebt));
+    // Last debt gets the remaining of the rounding
+    assert.equal(bobWrittenOff.recovered.toNumber(), recovered - aliceWrittenOff.recovered.toNumber());
+
+    let aliceUserAccountAfter = await program.account.userAccount.fetch(aliceUserAccount);
+    let bobUserAccountAfter = await program.account.userAccount.fetch(bobUserAccount);
+    assert.ok(aliceUserAccountAfter.baseLocked.eq(aliceUserAccountBefore.baseLocked.subn(100)));
+    assert.ok(aliceUserAccountAfter.baseFree.eq(aliceUserAccountBefore.baseFree.add(aliceWrittenOff.recovered)));
+    assert.ok(bobUserAccountAfter.baseLocked.eq(bobUserAccountBefore.baseLocked.subn(50)));
+    assert.ok(bobUserAccountAfter.baseFree.eq(bobUserAccountBefore.baseFree.add(bobWrittenOff.recovered)));
+    assert.equal(aliceUserAccountAfter.openDebtsCnt, aliceUserAccountBefore.openDebtsCnt - 1);
+    assert.equal(bobUserAccountAfter.openDebtsCnt, bobUserAccountBefore.openDebtsCnt - 1);
+
+    carolUserAccountData = await program.account.userAccount.fetch(carolUserAccount);
+    assert.equal(carolUserAccountData.openDebtsCnt, 0);
+    assert.equal(carolUserAccountData.baseFree.toNumber(), 0);
+    assert.equal(carolUserAccountData.quoteTotal.toNumber(), 0);
+    assert.ok((await program.account.debt.fetch(aliceDebt)).qty.eqn(0));
+    assert.ok((await program.account.debt.fetch(bobDebt)).qty.eqn(0));
+
+    let lexMarketAfter = await program.account.lexMarket.fetch(lexMarket.publicKey);
+    assert.ok(lexMarketAfter.totalBadDebt.eq(lexMarketBefore.totalBadDebt.add(resolvedEvent.shortfall)));
+    assert.ok(lexMarketAfter.openDebtsCnt.eq(lexMarketBefore.openDebtsCnt.subn(2)));
+
+    await program.rpc.setStubPrice(new anchor.BN(100000), new anchor.BN(10), {
+      accounts: {
+        admin: admin.publicKey,
+        stubPrice: stubPriceOracle.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }, signers: [admin, stubPriceOracle]
+    });
+  });
+
+  it('Anyone deposits to insurance fund', async () => {
+    const depositAccounts = {
+      depositor: bob.publicKey,
+      market: lexMarket.publicKey,
+      baseVault: lexBaseVault,
+      tokenSrc: bobAccountBase,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+
+    console.log("Ensure insurance is deposited in base");
+    await assert.rejects(
+      program.rpc.depositInsurance(new anchor.BN(10), {
+        accounts: {...depositAccounts, tokenSrc: bobAccountQuote},
+        signers: [bob]
+      })
+    );
+
+    let insuranceDeposit = new Promise<any>((resolve) => {
+      let listener = program.addEventListener("InsuranceDeposit", (event, _slot) => {
+        program.removeEventListener(listener);
+        resolve(event);
+      });
+    });
+
+    let insuranceBalance = (await program.account.lexMarket.fetch(lexMarket.publicKey)).insuranceBalance;
+    let vaultBalance = (await mintBase.getAccountInfo(lexBaseVault)).amount.toNumber();
+    await program.rpc.depositInsurance(new anchor.BN(10), {
+      accounts: depositAccounts,
+      signers: [bob]
+    });
+
+    assert.equal((await mintBase.getAccountInfo(lexBaseVault)).amount.toNumber(), vaultBalance + 10);
+    assert.ok((await program.account.lexMarket.fetch(lexMarket.publicKey)).insuranceBalance.eq(insuranceBalance.addn(10)));
+
+    let insuranceDepositEvent = await insuranceDeposit;
+    assert.ok(insuranceDepositEvent.depositor.equals(bob.publicKey));
+    assert.ok(insuranceDepositEvent.amount.eqn(10));
+    assert.ok(insuranceDepositEvent.insuranceBalance.eq(insuranceBalance.addn(10)));
+  });
+
+  it('Users close their accounts and admin closes the market', async () => {
+    const users = [
+      {user: alice, userAccount: aliceUserAccount, bump: aliceBump, base: aliceAccountBase, quote: aliceAccountQuote},
+      {user: bob, userAccount: bobUserAccount, bump: bobBump, base: bobAccountBase, quote: bobAccountQuote},
+      {user: carol, userAccount: carolUserAccount, bump: carolBump, base: carolAccountBase, quote: carolAccountQuote},
+    ];
+
+    console.log("Ensure user account with balance can't be closed");
+    await assert.rejects(
+      program.rpc.closeUserAccount(bobBump, {
+        accounts: {
+          owner: bob.publicKey,
+          userAccount: bobUserAccount,
+          market: lexMarket.publicKey,
+        },
+        signers: [bob]
+      })
+    );
+
+    console.log("Ensure market with balance in vaults can't be closed");
+    const closeMarketAccounts = {
+      admin: admin.publicKey,
+      market: lexMarket.publicKey,
+      marketSigner: marketSigner,
+      baseVault: lexBaseVault,
+      quoteVault: lexQuoteVault,
+      eventQueue: eventQueue.publicKey,
+      orderbook: orderbook.publicKey,
+      asks: asks.publicKey,
+      bids: bids.publicKey,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+    await assert.rejects(
+      program.rpc.closeMarket({
+        accounts: closeMarketAccounts,
+        signers: [admin]
+      })
+    );
+
+    for (const {user, userAccount, bump, base, quote} of users) {
+      let userAccountData = await program.account.userAccount.fetch(userAccount);
+
+      const withdrawals: [anchor.BN, anchor.web3.PublicKey, anchor.web3.PublicKey][] = [
+        [userAccountData.baseFree, lexBaseVault, base],
+        [userAccountData.quoteTotal, lexQuoteVault, quote],
+      ];
+      for (const [amount, vault, tokenDest] of withdrawals) {
+        if (amount.eqn(0)) {
+          continue;
+        }
+        await program.rpc.withdraw(bump, amount, {
+          accounts: {
+            owner: user.publicKey,
+            userAccount: userAccount,
+            market: lexMarket.publicKey,
+            marketSigner: marketSigner,
+            vault: vault,
+            tokenDest: tokenDest,
+            priceOracle: stubPriceOracle.publicKey,
+            tokenProgram: spl_token.TOKEN_PROGRAM_ID
+          },
+          signers: [user]
+        });
+      }
+
+      await program.rpc.closeUserAccount(bump, {
+        accounts: {
+          owner: user.publicKey,
+          userAccount: userAccount,
+          market: lexMarket.publicKey,
+        },
+        signers: [user]
+      });
+      assert.equal(await connection.getAccountInfo(userAccount), null);
+    }
+
+    console.log("Admin claims protocol fees");
+    let adminAccountBase = await mintBase.createAccount(admin.publicKey);
+    let lexMarketData = await program.account.lexMarket.fetch(lexMarket.publicKey);
+    let feeBalance = lexMarketData.feeBalance.toNumber();
+    assert.ok(lexMarketData.totalFees.toNumber() >= feeBalance);
+
+    await assert.rejects(
+      program.rpc.claimFees({
+        accounts: {
+          feeAdmin: alice.publicKey,
+          market: lexMarket.publicKey,
+          marketSigner: marketSigner,
+          baseVault: lexBaseVault,
+          tokenDest: aliceAccountBase,
+          tokenProgram: spl_token.TOKEN_PROGRAM_ID
+        },
+        signers: [alice]
+      })
+    );
+
+    await program.rpc.claimFees({
+      accounts: {
+        feeAdmin: admin.publicKey,
+        market: lexMarket.publicKey,
+        marketSigner: marketSigner,
+        baseVault: lexBaseVault,
+        tokenDest: adminAccountBase,
+        tokenProgram: spl_token.TOKEN_PROGRAM_ID
+      },
+      signers: [admin]
+    });
+
+    assert.equal((await mintBase.getAccountInfo(adminAccountBase)).amount.toNumber(), feeBalance);
+    lexMarketData = await program.account.lexMarket.fetch(lexMarket.publicKey);
+    assert.equal(lexMarketData.feeBalance.toNumber(), 0);
+
+    console.log("Admin retires insurance fund");
+    const retireInsuranceAccounts = {
+      admin: admin.publicKey,
+      market: lexMarket.publicKey,
+      marketSigner: marketSigner,
+      baseVault: lexBaseVault,
+      tokenDest: adminAccountBase,
+      tokenProgram: spl_token.TOKEN_PROGRAM_ID
+    };
+    console.log("Ensure insurance can't be retired while market is live");
+    await assert.rejects(
+      program.rpc.retireInsurance({
+        accounts: retireInsuranceAccounts,
+        signers: [admin]
+      })
+    );
+
+    await program.rpc.setMarketStatus({paused: {}}, {
+      accounts: {
+        authority: alice.publicKey,
+        market: lexMarket.publicKey,
+      },
+      signers: [alice]
+    });
+
+    console.log("Ensure only admin can retire insurance and only to its own token account");
+    await assert.rejects(
+      program.rpc.retireInsurance({
+        accounts: {...retireInsuranceAccounts, admin: alice.publicKey, tokenDest: aliceAccountBase},
+        signers: [alice]
+      })
+    );
+    await assert.rejects(
+      program.rpc.retireInsurance({
+        accounts: {...retireInsuranceAccounts, tokenDest: aliceAccountBase},
+        signers: [admin]
+      })
+    );
+
+    let insuranceBalance = lexMarketData.insuranceBalance.toNumber();
+    assert.ok(insuranceBalance > 0);
+    await program.rpc.retireInsurance({
+      accounts: retireInsuranceAccounts,
+      signers: [admin]
+    });
+
+    assert.equal((await mintBase.getAccountInfo(adminAccountBase)).amount.toNumber(), feeBalance + insuranceBalance);
+    assert.equal((await program.account.lexMarket.fetch(lexMarket.publicKey)).insuranceBalance.toNumber(), 0);
+    assert.equal((await mintBase.getAccountInfo(lexBaseVault)).amount.toNumber(), 0);
+
+    console.log("Ensure only admin can close the market");
+    await assert.rejects(
+      program.rpc.closeMarket({
+        accounts: {...closeMarketAccounts, admin: alice.publicKey},
+        signers: [alice]
+      })
+    );
+
+    let adminBalanceBefore = await connection.getBalance(admin.publicKey);
+    await program.rpc.closeMarket({
+      accounts: closeMarketAccounts,
+      signers: [admin]
+    });
+
+    for (const account of [lexMarket.publicKey, orderbook.publicKey, eventQueue.publicKey, asks.publicKey, bids.publicKey, lexBaseVault, lexQuoteVault]) {
+      assert.equal(await connection.getAccountInfo(account), null);
+    }
+    assert.ok(await connection.getBalance(admin.publicKey) > adminBalanceBefore);
   });
 
 });